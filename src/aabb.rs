@@ -0,0 +1,116 @@
+use crate::interval::{self, Interval};
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+pub const EMPTY: Aabb = Aabb {
+    x: interval::EMPTY,
+    y: interval::EMPTY,
+    z: interval::EMPTY,
+};
+
+const MIN_EXTENT: f32 = 1e-4;
+
+impl Aabb {
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Aabb {
+        Aabb { x, y, z }
+    }
+
+    /// Box spanning the two corner points `a` and `b`, in any order.
+    pub fn from_points(a: Vec3, b: Vec3) -> Aabb {
+        Aabb {
+            x: Interval::new(a.x().min(b.x()), a.x().max(b.x())),
+            y: Interval::new(a.y().min(b.y()), a.y().max(b.y())),
+            z: Interval::new(a.z().min(b.z()), a.z().max(b.z())),
+        }
+    }
+
+    pub fn enclosing(a: Aabb, b: Aabb) -> Aabb {
+        Aabb {
+            x: Interval::enclosing(a.x, b.x),
+            y: Interval::enclosing(a.y, b.y),
+            z: Interval::enclosing(a.z, b.z),
+        }
+    }
+
+    /// Returns a copy with every axis at least `MIN_EXTENT` wide, so that flat
+    /// primitives don't produce degenerate boxes.
+    pub fn padded(&self) -> Aabb {
+        let pad = |i: Interval| {
+            if i.size() < MIN_EXTENT {
+                i.expand(MIN_EXTENT)
+            } else {
+                i
+            }
+        };
+        Aabb::new(pad(self.x), pad(self.y), pad(self.z))
+    }
+
+    pub fn axis(&self, n: usize) -> Interval {
+        match n {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        Vec3::new(
+            (self.x.min + self.x.max) / 2.0,
+            (self.y.min + self.y.max) / 2.0,
+            (self.z.min + self.z.max) / 2.0,
+        )
+    }
+
+    pub fn hit(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.clip(ray, ray_t).is_some()
+    }
+
+    /// Part of `ray_t` over which `ray` is inside the box, if any.
+    pub fn clip(&self, ray: &Ray, ray_t: Interval) -> Option<Interval> {
+        let mut t_min = ray_t.min;
+        let mut t_max = ray_t.max;
+
+        for n in 0..3 {
+            let axis = self.axis(n);
+            let inv_d = 1.0 / ray.direction.e[n];
+            let origin = ray.origin.e[n];
+
+            let mut t0 = (axis.min - origin) * inv_d;
+            let mut t1 = (axis.max - origin) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some(Interval::new(t_min, t_max))
+    }
+}
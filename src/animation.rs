@@ -0,0 +1,290 @@
+use crate::camera::{CameraBuilder, CameraError};
+use crate::hittable::Hittable;
+use crate::vec3::Vec3;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::ops::{Add, Mul, Range, Sub};
+
+/// Camera parameters at a given frame. `fov` is ignored when the camera uses
+/// a physical focal length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub frame: f32,
+    pub lookfrom: Vec3,
+    pub lookat: Vec3,
+    pub fov: f32,
+    pub focus_dist: f32,
+}
+
+impl Keyframe {
+    pub fn new(frame: f32, lookfrom: Vec3, lookat: Vec3, fov: f32, focus_dist: f32) -> Keyframe {
+        Keyframe {
+            frame,
+            lookfrom,
+            lookat,
+            fov,
+            focus_dist,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    CatmullRom,
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    EmptyPath,
+    Camera(CameraError),
+    Io(io::Error),
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnimationError::EmptyPath => write!(f, "camera path has no keyframes"),
+            AnimationError::Camera(err) => write!(f, "invalid camera: {}", err),
+            AnimationError::Io(err) => write!(f, "unable to write frame: {}", err),
+        }
+    }
+}
+
+impl Error for AnimationError {}
+
+impl From<CameraError> for AnimationError {
+    fn from(err: CameraError) -> AnimationError {
+        AnimationError::Camera(err)
+    }
+}
+
+impl From<io::Error> for AnimationError {
+    fn from(err: io::Error) -> AnimationError {
+        AnimationError::Io(err)
+    }
+}
+
+pub struct CameraPath {
+    pub interpolation: Interpolation,
+    keyframes: Vec<Keyframe>,
+}
+
+fn lerp<T>(a: T, b: T, t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    a + (b - a) * t
+}
+
+/// Cubic Hermite interpolation between `p1` and `p2` with tangents `m1`, `m2`.
+fn hermite<T>(p1: T, p2: T, m1: T, m2: T, t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    p1 * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + m1 * (t3 - 2.0 * t2 + t)
+        + p2 * (-2.0 * t3 + 3.0 * t2)
+        + m2 * (t3 - t2)
+}
+
+impl CameraPath {
+    pub fn new(interpolation: Interpolation) -> CameraPath {
+        CameraPath {
+            interpolation,
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn keyframe(mut self, keyframe: Keyframe) -> CameraPath {
+        let idx = self
+            .keyframes
+            .partition_point(|k| k.frame <= keyframe.frame);
+        self.keyframes.insert(idx, keyframe);
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Catmull-Rom tangent at keyframe `k`, scaled to the segment `[k1, k2]`
+    /// so that unevenly spaced keyframes don't overshoot.
+    fn tangent<T>(&self, value: fn(&Keyframe) -> T, k: usize, k1: usize, k2: usize) -> T
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+    {
+        let last = self.keyframes.len() - 1;
+        let prev = k.saturating_sub(1);
+        let next = (k + 1).min(last);
+
+        let span = self.keyframes[next].frame - self.keyframes[prev].frame;
+        let segment = self.keyframes[k2].frame - self.keyframes[k1].frame;
+
+        (value(&self.keyframes[next]) - value(&self.keyframes[prev])) * (segment / span)
+    }
+
+    /// Interpolates the keyframe parameter read by `value` between keyframes
+    /// `k1` and `k2`.
+    fn interpolate<T>(&self, value: fn(&Keyframe) -> T, k1: usize, k2: usize, t: f32) -> T
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+    {
+        let p1 = value(&self.keyframes[k1]);
+        let p2 = value(&self.keyframes[k2]);
+        match self.interpolation {
+            Interpolation::Linear => lerp(p1, p2, t),
+            Interpolation::CatmullRom => {
+                let m1 = self.tangent(value, k1, k1, k2);
+                let m2 = self.tangent(value, k2, k1, k2);
+                hermite(p1, p2, m1, m2, t)
+            }
+        }
+    }
+
+    /// Camera parameters at `frame`, held constant before the first and after
+    /// the last keyframe.
+    pub fn sample(&self, frame: f32) -> Result<Keyframe, AnimationError> {
+        let (Some(&first), Some(&last)) = (self.keyframes.first(), self.keyframes.last()) else {
+            return Err(AnimationError::EmptyPath);
+        };
+        if frame <= first.frame {
+            return Ok(Keyframe { frame, ..first });
+        }
+        if frame >= last.frame {
+            return Ok(Keyframe { frame, ..last });
+        }
+
+        let k2 = self.keyframes.partition_point(|k| k.frame <= frame);
+        let k1 = k2 - 1;
+        let t = (frame - self.keyframes[k1].frame)
+            / (self.keyframes[k2].frame - self.keyframes[k1].frame);
+
+        Ok(Keyframe {
+            frame,
+            lookfrom: self.interpolate(|k| k.lookfrom, k1, k2, t),
+            lookat: self.interpolate(|k| k.lookat, k1, k2, t),
+            fov: self.interpolate(|k| k.fov, k1, k2, t),
+            focus_dist: self.interpolate(|k| k.focus_dist, k1, k2, t),
+        })
+    }
+
+    /// Returns `builder` with the animated parameters set for `frame`.
+    pub fn apply(
+        &self,
+        builder: &CameraBuilder,
+        frame: f32,
+    ) -> Result<CameraBuilder, AnimationError> {
+        let keyframe = self.sample(frame)?;
+
+        Ok(builder
+            .clone()
+            .lookfrom(keyframe.lookfrom)
+            .lookat(keyframe.lookat)
+            .fov(keyframe.fov)
+            .focus_dist(keyframe.focus_dist))
+    }
+}
+
+pub fn frame_path(prefix: &str, frame: u32) -> String {
+    format!("{}_{:04}.ppm", prefix, frame)
+}
+
+/// Renders every frame in `frames` along `path` and writes them as a numbered
+/// image sequence (`<prefix>_0001.ppm`, ...). The world, including any BVH it
+/// contains, is built once by the caller and shared by all frames.
+pub fn render_sequence(
+    builder: &CameraBuilder,
+    path: &CameraPath,
+    frames: Range<u32>,
+    world: &dyn Hittable,
+    prefix: &str,
+) -> Result<(), AnimationError> {
+    for frame in frames {
+        let camera = path.apply(builder, frame as f32)?.build()?;
+        let img = camera.render(world);
+        img.write_ppm(&frame_path(prefix, frame))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(interpolation: Interpolation) -> CameraPath {
+        CameraPath::new(interpolation)
+            .keyframe(Keyframe::new(
+                10.0,
+                Vec3::new(10.0, 0.0, 0.0),
+                Vec3::zero(),
+                40.0,
+                10.0,
+            ))
+            .keyframe(Keyframe::new(
+                0.0,
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::zero(),
+                20.0,
+                5.0,
+            ))
+            .keyframe(Keyframe::new(
+                20.0,
+                Vec3::new(20.0, 0.0, 0.0),
+                Vec3::zero(),
+                60.0,
+                15.0,
+            ))
+    }
+
+    #[test]
+    fn test_linear_interpolation() {
+        let path = path(Interpolation::Linear);
+        let keyframe = path.sample(5.0).unwrap();
+        assert_eq!(keyframe.lookfrom, Vec3::new(5.0, 0.0, 0.0));
+        assert_eq!(keyframe.fov, 30.0);
+        assert_eq!(keyframe.focus_dist, 7.5);
+    }
+
+    #[test]
+    fn test_catmull_rom_passes_through_keyframes() {
+        let path = path(Interpolation::CatmullRom);
+        assert_eq!(
+            path.sample(10.0).unwrap().lookfrom,
+            Vec3::new(10.0, 0.0, 0.0)
+        );
+        assert!((path.sample(15.0).unwrap().lookfrom.x() - 15.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sample_is_clamped_outside_keyframes() {
+        let path = path(Interpolation::Linear);
+        assert_eq!(path.sample(-5.0).unwrap().fov, 20.0);
+        assert_eq!(path.sample(25.0).unwrap().fov, 60.0);
+    }
+
+    #[test]
+    fn test_render_sequence_errors() {
+        let empty = CameraPath::new(Interpolation::Linear);
+        let world: Vec<Box<dyn Hittable>> = Vec::new();
+
+        assert!(matches!(empty.sample(0.0), Err(AnimationError::EmptyPath)));
+        let result = render_sequence(&CameraBuilder::new(), &empty, 0..1, &world, "unused");
+        assert!(matches!(result, Err(AnimationError::EmptyPath)));
+
+        let builder = CameraBuilder::new().img_width(2).samples_per_pixel(1);
+        let prefix = "/nonexistent/frames/img";
+        let result = render_sequence(&builder, &path(Interpolation::Linear), 5..6, &world, prefix);
+        assert!(matches!(result, Err(AnimationError::Io(_))));
+    }
+
+    #[test]
+    fn test_frame_path() {
+        assert_eq!(frame_path("out/img", 7), "out/img_0007.ppm");
+    }
+}
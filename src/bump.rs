@@ -0,0 +1,146 @@
+use crate::hittable::HitRecord;
+use crate::material::{Material, Scatter, Scatterable};
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// Step in texture space used to take finite differences of height maps.
+const HEIGHT_DELTA: f32 = 1e-3;
+
+#[derive(Clone)]
+pub enum BumpMap {
+    /// Tangent-space normal map with the usual `[0, 1] -> [-1, 1]` encoding.
+    /// `strength` scales the tangential part of the decoded normal.
+    Normal { texture: Texture, strength: f32 },
+    /// Height map displacing the surface along its normal by `scale` scene
+    /// units per unit of texture value.
+    Height { texture: Texture, scale: f32 },
+}
+
+/// Perturbs the shading normal of the wrapped material.
+#[derive(Clone)]
+pub struct Bump {
+    pub material: Material,
+    pub map: BumpMap,
+}
+
+impl Bump {
+    pub fn perturb<'a>(&self, hit_rec: &HitRecord<'a>) -> HitRecord<'a> {
+        let n = hit_rec.normal;
+
+        let normal = match &self.map {
+            BumpMap::Normal { texture, strength } => {
+                let onb = Onb::from_normal_tangent(n, hit_rec.tangent);
+                // Keep the bitangent's handedness from the surface parametrization.
+                let handedness = if onb.v.dot(hit_rec.bitangent) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+
+                let encoded = texture.value(hit_rec.u, hit_rec.v);
+                let x = (2.0 * encoded.x() - 1.0) * strength;
+                let y = (2.0 * encoded.y() - 1.0) * strength * handedness;
+                let z = 2.0 * encoded.z() - 1.0;
+
+                onb.local(Vec3::new(x, y, z))
+            }
+            BumpMap::Height { texture, scale } => {
+                let (u, v) = (hit_rec.u, hit_rec.v);
+                let h = texture.scalar(u, v);
+                let dhdu = (texture.scalar(u + HEIGHT_DELTA, v) - h) / HEIGHT_DELTA;
+                let dhdv = (texture.scalar(u, v + HEIGHT_DELTA) - h) / HEIGHT_DELTA;
+
+                let dpdu = hit_rec.tangent + scale * dhdu * n;
+                let dpdv = hit_rec.bitangent + scale * dhdv * n;
+                let perturbed = dpdu.cross(dpdv);
+
+                if perturbed.dot(n) < 0.0 {
+                    -perturbed
+                } else {
+                    perturbed
+                }
+            }
+        };
+
+        let normal = if normal.near_zero() || normal.dot(n) <= 0.0 {
+            n
+        } else {
+            normal.unit_vector()
+        };
+
+        HitRecord { normal, ..*hit_rec }
+    }
+}
+
+impl Scatterable for Bump {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        self.material.scatter(ray_in, &self.perturb(hit_rec))
+    }
+
+    fn emitted(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.material.emitted(hit_rec)
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        self.material
+            .eval(ray_in, &self.perturb(hit_rec), direction)
+    }
+
+    fn albedo(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.material.albedo(hit_rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{hit_record, material};
+
+    fn hit_rec(material: &Material) -> HitRecord<'_> {
+        HitRecord {
+            tangent: Vec3::new(2.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 2.0, 0.0),
+            ..hit_record(material)
+        }
+    }
+
+    fn bump(map: BumpMap) -> Bump {
+        Bump {
+            material: material(),
+            map,
+        }
+    }
+
+    #[test]
+    fn test_flat_normal_map_keeps_normal() {
+        let bump = bump(BumpMap::Normal {
+            texture: Texture::Solid(Vec3::new(0.5, 0.5, 1.0)),
+            strength: 1.0,
+        });
+        let perturbed = bump.perturb(&hit_rec(&bump.material));
+        assert!((perturbed.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_normal_map_tilts_along_tangent() {
+        let bump = bump(BumpMap::Normal {
+            texture: Texture::Solid(Vec3::new(0.75, 0.5, 1.0)),
+            strength: 1.0,
+        });
+        let perturbed = bump.perturb(&hit_rec(&bump.material));
+        let expected = Vec3::new(0.5, 0.0, 1.0).unit_vector();
+        assert!((perturbed.normal - expected).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_flat_height_map_keeps_normal() {
+        let bump = bump(BumpMap::Height {
+            texture: Texture::Solid(Vec3::zero()),
+            scale: 1.0,
+        });
+        let perturbed = bump.perturb(&hit_rec(&bump.material));
+        assert!((perturbed.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+    }
+}
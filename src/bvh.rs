@@ -0,0 +1,149 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+/// Bounding volume hierarchy over a list of hittables.
+///
+/// Objects are split at the median centroid along the longest axis of the
+/// node's bounding box. Build it once per scene and reuse it across renders.
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    right: Option<Box<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(objects: Vec<Box<dyn Hittable>>) -> BvhNode {
+        assert!(!objects.is_empty(), "BVH requires at least one object");
+
+        let mut objects: Vec<(Aabb, Box<dyn Hittable>)> = objects
+            .into_iter()
+            .map(|object| (object.bounding_box(), object))
+            .collect();
+
+        BvhNode::build(&mut objects)
+    }
+
+    fn build(objects: &mut Vec<(Aabb, Box<dyn Hittable>)>) -> BvhNode {
+        let bbox = objects
+            .iter()
+            .skip(1)
+            .fold(objects[0].0, |bbox, (object_bbox, _)| {
+                Aabb::enclosing(bbox, *object_bbox)
+            });
+
+        match objects.len() {
+            1 => {
+                let (_, left) = objects.pop().unwrap();
+                BvhNode {
+                    left,
+                    right: None,
+                    bbox,
+                }
+            }
+            2 => {
+                let (_, right) = objects.pop().unwrap();
+                let (_, left) = objects.pop().unwrap();
+                BvhNode {
+                    left,
+                    right: Some(right),
+                    bbox,
+                }
+            }
+            _ => {
+                let axis = bbox.longest_axis();
+                objects.sort_by(|(a, _), (b, _)| {
+                    a.centroid().e[axis].total_cmp(&b.centroid().e[axis])
+                });
+
+                let mut right_objects = objects.split_off(objects.len() / 2);
+                let left = BvhNode::build(objects);
+                let right = BvhNode::build(&mut right_objects);
+
+                BvhNode {
+                    left: Box::new(left),
+                    right: Some(Box::new(right)),
+                    bbox,
+                }
+            }
+        }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        if !self.bbox.hit(ray, ray_t) {
+            return None;
+        }
+
+        let left_hit = self.left.hit(ray, ray_t);
+        let closest = left_hit.map_or(ray_t.max, |hit_rec| hit_rec.t);
+
+        let right_hit = self
+            .right
+            .as_ref()
+            .and_then(|right| right.hit(ray, Interval::new(ray_t.min, closest)));
+
+        right_hit.or(left_hit)
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f32 {
+        if !self.bbox.hit(ray, ray_t) {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(ray, ray_t);
+        match &self.right {
+            Some(right) if left > 0.0 => left * right.transmittance(ray, ray_t),
+            _ => left,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+    use crate::sphere::Sphere;
+    use crate::testing::material;
+    use crate::vec3::Vec3;
+
+    fn spheres(params: &[(Vec3, f32)]) -> Vec<Box<dyn Hittable>> {
+        params
+            .iter()
+            .map(|(center, radius)| {
+                let sphere: Box<dyn Hittable> = Box::new(Sphere::new(*center, *radius, material()));
+                sphere
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_linear_search() {
+        let params: Vec<(Vec3, f32)> = (0..100)
+            .map(|_| {
+                (
+                    Vec3::random_in_range(-10.0, 10.0),
+                    rng::random_in_range(0.1, 1.0),
+                )
+            })
+            .collect();
+        let list = spheres(&params);
+        let bvh = BvhNode::new(spheres(&params));
+
+        for _ in 0..1000 {
+            let ray = Ray::new(
+                Vec3::random_in_range(-20.0, 20.0),
+                Vec3::random_unit_vector(),
+            );
+            let ray_t = Interval::new(0.001, f32::INFINITY);
+            let expected = list.hit(&ray, ray_t).map(|hit_rec| hit_rec.t);
+            assert_eq!(bvh.hit(&ray, ray_t).map(|hit_rec| hit_rec.t), expected);
+        }
+    }
+}
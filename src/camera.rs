@@ -1,24 +1,213 @@
+use crate::framebuffer::FrameBuffer;
+use crate::hittable::HitRecord;
 use crate::hittable::Hittable;
+use crate::image::Image;
 use crate::interval::Interval;
+use crate::light::{Light, LightGroup};
 use crate::material::Scatterable;
 use crate::ray::Ray;
 use crate::rng;
+use crate::sky::Sky;
+use crate::spectrum;
 use crate::vec3::Vec3;
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::Write;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+/// How camera rays are generated for each pixel.
+///
+/// `Fisheye` uses an equidistant mapping where `fov` spans the image width, and
+/// `Equirectangular` covers the full sphere around the camera. Both are pinhole
+/// projections, so depth of field only affects `Perspective`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic { view_height: f32 },
+    Fisheye,
+    Equirectangular,
+}
+
+/// Shape of the lens aperture, which determines the shape of out-of-focus
+/// highlights. Masks are stretched over the square around the unit disk and
+/// sampled by their luminance; pixels centered outside the disk are ignored.
+#[derive(Clone)]
+pub enum Aperture {
+    Circular,
+    Polygonal { blades: u32, rotation: f32 },
+    Mask(Arc<Image>),
+}
+
+/// Position of the center of mask pixel `(i, j)` on the aperture.
+fn mask_point(mask: &Image, i: f32, j: f32) -> (f32, f32) {
+    (
+        2.0 * i / mask.width as f32 - 1.0,
+        1.0 - 2.0 * j / mask.height as f32,
+    )
+}
+
+/// Running sum of the luminance of the mask pixels inside the unit disk, in
+/// row-major order.
+fn mask_cdf(mask: &Image) -> Vec<f32> {
+    let mut total = 0.0;
+    (0..mask.height)
+        .flat_map(|j| (0..mask.width).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let (x, y) = mask_point(mask, i as f32 + 0.5, j as f32 + 0.5);
+            if x * x + y * y <= 1.0 {
+                let value = mask.pixel(i, j);
+                total += ((value.x() + value.y() + value.z()) / 3.0).max(0.0);
+            }
+            total
+        })
+        .collect()
+}
+
+impl Aperture {
+    /// Returns a point on the aperture scaled to fit the unit disk. `mask_cdf`
+    /// holds the [`mask_cdf`] of a mask aperture.
+    fn sample(&self, mask_cdf: &[f32]) -> (f32, f32) {
+        match self {
+            Aperture::Circular => {
+                let p = Vec3::random_in_unit_disk();
+                (p.x(), p.y())
+            }
+            Aperture::Polygonal { blades, rotation } => {
+                let blades = (*blades).max(3);
+                let sector = 2.0 * PI / blades as f32;
+                let k = ((rng::random() * blades as f32) as u32).min(blades - 1);
+                let a0 = rotation.to_radians() + k as f32 * sector;
+                let a1 = a0 + sector;
+
+                let r1 = rng::random().sqrt();
+                let r2 = rng::random();
+                let b0 = r1 * (1.0 - r2);
+                let b1 = r1 * r2;
+
+                (b0 * a0.cos() + b1 * a1.cos(), b0 * a0.sin() + b1 * a1.sin())
+            }
+            Aperture::Mask(mask) => {
+                let total = mask_cdf[mask_cdf.len() - 1];
+                let target = rng::random() * total;
+                let index = mask_cdf
+                    .partition_point(|&sum| sum <= target)
+                    .min(mask_cdf.len() - 1);
+                let i = index as u32 % mask.width;
+                let j = index as u32 / mask.width;
+
+                mask_point(mask, i as f32 + rng::random(), j as f32 + rng::random())
+            }
+        }
+    }
+}
+
+/// What the camera should focus on when autofocusing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FocusTarget {
+    Lookat,
+    Pixel(u32, u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraError {
+    ZeroWidth,
+    InvalidAspectRatio(f32),
+    ZeroHeight { img_width: u32, aspect_ratio: f32 },
+    ZeroSamplesPerPixel,
+    InvalidFov(f32),
+    InvalidFocusDist(f32),
+    InvalidFocalLength(f32),
+    InvalidSensorSize { width: f32, height: f32 },
+    InvalidFStop(f32),
+    InvalidViewHeight(f32),
+    EmptyApertureMask,
+    LookfromEqualsLookat,
+    VupParallelToView,
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraError::ZeroWidth => write!(f, "image width must be greater than zero"),
+            CameraError::InvalidAspectRatio(aspect_ratio) => {
+                write!(f, "aspect ratio must be positive, got {}", aspect_ratio)
+            }
+            CameraError::ZeroHeight {
+                img_width,
+                aspect_ratio,
+            } => write!(
+                f,
+                "image width {} with aspect ratio {} results in zero image height",
+                img_width, aspect_ratio
+            ),
+            CameraError::ZeroSamplesPerPixel => {
+                write!(f, "samples per pixel must be greater than zero")
+            }
+            CameraError::InvalidFov(fov) => {
+                write!(f, "field of view must be in (0, 180) degrees, got {}", fov)
+            }
+            CameraError::InvalidFocusDist(focus_dist) => {
+                write!(f, "focus distance must be positive, got {}", focus_dist)
+            }
+            CameraError::InvalidFocalLength(focal_length) => {
+                write!(f, "focal length must be positive, got {}", focal_length)
+            }
+            CameraError::InvalidSensorSize { width, height } => {
+                write!(f, "sensor size must be positive, got {}x{}", width, height)
+            }
+            CameraError::InvalidFStop(f_stop) => {
+                write!(f, "f-stop must be positive, got {}", f_stop)
+            }
+            CameraError::InvalidViewHeight(view_height) => write!(
+                f,
+                "orthographic view height must be positive, got {}",
+                view_height
+            ),
+            CameraError::EmptyApertureMask => {
+                write!(
+                    f,
+                    "aperture mask must have a lit pixel inside the unit disk"
+                )
+            }
+            CameraError::LookfromEqualsLookat => {
+                write!(f, "lookfrom and lookat must be different points")
+            }
+            CameraError::VupParallelToView => {
+                write!(f, "vup must not be parallel to the view direction")
+            }
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+fn is_positive(x: f32) -> bool {
+    x.is_finite() && x > 0.0
+}
 
 pub struct Camera {
     pub aspect_ratio: f32,
     pub img_width: u32,
     pub samples_per_pixel: u32,
     pub max_depth: u32,
-    pub fov: u32,
+    pub fov: f32,
     pub lookfrom: Vec3,
     pub lookat: Vec3,
     pub vup: Vec3,
     pub defocus_angle: f32,
     pub focus_dist: f32,
+    pub focal_length: Option<f32>,
+    pub sensor_width: f32,
+    pub sensor_height: f32,
+    pub f_stop: Option<f32>,
+    pub units_per_meter: f32,
+    pub aperture: Aperture,
+    pub projection: Projection,
+    pub spectral: bool,
+    /// Physical sky in place of the default gradient background.
+    pub sky: Option<Sky>,
+    aperture_cdf: Vec<f32>,
     img_height: u32,
     center: Vec3,
     pixel00_loc: Vec3,
@@ -27,21 +216,33 @@ pub struct Camera {
     u: Vec3,
     v: Vec3,
     w: Vec3,
+    defocus_radius: f32,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
 }
 
+#[derive(Clone)]
 pub struct CameraBuilder {
     pub aspect_ratio: f32,
     pub img_width: u32,
     pub samples_per_pixel: u32,
     pub max_depth: u32,
-    pub fov: u32,
+    pub fov: f32,
     pub lookfrom: Vec3,
     pub lookat: Vec3,
     pub vup: Vec3,
     pub defocus_angle: f32,
     pub focus_dist: f32,
+    pub focal_length: Option<f32>,
+    pub sensor_width: f32,
+    pub sensor_height: f32,
+    pub f_stop: Option<f32>,
+    pub units_per_meter: f32,
+    pub aperture: Aperture,
+    pub projection: Projection,
+    pub spectral: bool,
+    /// Physical sky in place of the default gradient background.
+    pub sky: Option<Sky>,
 }
 
 impl CameraBuilder {
@@ -51,12 +252,21 @@ impl CameraBuilder {
             img_width: 100,
             samples_per_pixel: 10,
             max_depth: 10,
-            fov: 90,
+            fov: 90.0,
             lookfrom: Vec3::new(0.0, 0.0, -1.0),
             lookat: Vec3::new(0.0, 0.0, 0.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            focal_length: None,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            f_stop: None,
+            units_per_meter: 1.0,
+            aperture: Aperture::Circular,
+            projection: Projection::Perspective,
+            spectral: false,
+            sky: None,
         }
     }
 
@@ -80,7 +290,7 @@ impl CameraBuilder {
         self
     }
 
-    pub fn fov(mut self, fov: u32) -> CameraBuilder {
+    pub fn fov(mut self, fov: f32) -> CameraBuilder {
         self.fov = fov;
         self
     }
@@ -110,14 +320,202 @@ impl CameraBuilder {
         self
     }
 
-    pub fn build(&self) -> Camera {
+    /// Focal length of the lens in millimeters. Overrides `fov`.
+    pub fn focal_length(mut self, focal_length: f32) -> CameraBuilder {
+        self.focal_length = Some(focal_length);
+        self
+    }
+
+    /// Sensor dimensions in millimeters, defaults to a 36x24 full frame.
+    pub fn sensor_size(mut self, sensor_width: f32, sensor_height: f32) -> CameraBuilder {
+        self.sensor_width = sensor_width;
+        self.sensor_height = sensor_height;
+        self
+    }
+
+    /// Relative aperture of the lens. Together with the focal length this
+    /// overrides `defocus_angle`.
+    pub fn f_stop(mut self, f_stop: f32) -> CameraBuilder {
+        self.f_stop = Some(f_stop);
+        self
+    }
+
+    /// Number of scene units in one meter, used to size the physical aperture.
+    pub fn units_per_meter(mut self, units_per_meter: f32) -> CameraBuilder {
+        self.units_per_meter = units_per_meter;
+        self
+    }
+
+    pub fn aperture(mut self, aperture: Aperture) -> CameraBuilder {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn projection(mut self, projection: Projection) -> CameraBuilder {
+        self.projection = projection;
+        self
+    }
+
+    /// Renders with a spectral integrator: every camera ray carries a single
+    /// wavelength, RGB inputs are upsampled to spectra and the samples are
+    /// accumulated in XYZ before converting to RGB.
+    pub fn spectral(mut self, spectral: bool) -> CameraBuilder {
+        self.spectral = spectral;
+        self
+    }
+
+    /// Lights misses with `sky`. Its sun only lights the scene when
+    /// `sky.sun_light()` is part of the lights it is rendered with.
+    pub fn sky(mut self, sky: Sky) -> CameraBuilder {
+        self.sky = Some(sky);
+        self
+    }
+
+    /// Sets `focus_dist` to the distance of the first surface hit by a ray
+    /// through the target, measured along the view direction. Call it after
+    /// the camera geometry is configured; if nothing is hit, or the camera is
+    /// not valid yet, `focus_dist` is left unchanged.
+    pub fn autofocus(mut self, target: FocusTarget, world: &dyn Hittable) -> CameraBuilder {
+        let pinhole = CameraBuilder {
+            defocus_angle: 0.0,
+            f_stop: None,
+            ..self.clone()
+        };
+
+        if let Ok(camera) = pinhole.build() {
+            let ray = match target {
+                FocusTarget::Lookat => Ray::new(camera.center, self.lookat - self.lookfrom),
+                FocusTarget::Pixel(i, j) => camera.ray_at(
+                    (i as f32 + 0.5) / camera.img_width as f32,
+                    (j as f32 + 0.5) / camera.img_height as f32,
+                ),
+            };
+
+            if let Some(hit_rec) = world.hit(&ray, Interval::new(0.001, f32::INFINITY)) {
+                let focus_dist = (hit_rec.point - camera.center).dot(-camera.w);
+                if is_positive(focus_dist) {
+                    self.focus_dist = focus_dist;
+                }
+            }
+        }
+
+        self
+    }
+
+    fn validate(&self) -> Result<(), CameraError> {
+        if self.img_width == 0 {
+            return Err(CameraError::ZeroWidth);
+        }
+        if !is_positive(self.aspect_ratio) {
+            return Err(CameraError::InvalidAspectRatio(self.aspect_ratio));
+        }
+        if (self.img_width as f32 / self.aspect_ratio) as u32 == 0 {
+            return Err(CameraError::ZeroHeight {
+                img_width: self.img_width,
+                aspect_ratio: self.aspect_ratio,
+            });
+        }
+        if self.samples_per_pixel == 0 {
+            return Err(CameraError::ZeroSamplesPerPixel);
+        }
+        if !is_positive(self.focus_dist) {
+            return Err(CameraError::InvalidFocusDist(self.focus_dist));
+        }
+        if !is_positive(self.sensor_width) || !is_positive(self.sensor_height) {
+            return Err(CameraError::InvalidSensorSize {
+                width: self.sensor_width,
+                height: self.sensor_height,
+            });
+        }
+        if let Some(focal_length) = self.focal_length {
+            if !is_positive(focal_length) {
+                return Err(CameraError::InvalidFocalLength(focal_length));
+            }
+        }
+        if let Some(f_stop) = self.f_stop {
+            if !is_positive(f_stop) {
+                return Err(CameraError::InvalidFStop(f_stop));
+            }
+        }
+        match self.projection {
+            Projection::Perspective => {
+                if self.focal_length.is_none() && !(self.fov > 0.0 && self.fov < 180.0) {
+                    return Err(CameraError::InvalidFov(self.fov));
+                }
+            }
+            Projection::Orthographic { view_height } => {
+                if !is_positive(view_height) {
+                    return Err(CameraError::InvalidViewHeight(view_height));
+                }
+            }
+            Projection::Fisheye => {
+                if self.focal_length.is_none() && !is_positive(self.fov) {
+                    return Err(CameraError::InvalidFov(self.fov));
+                }
+            }
+            Projection::Equirectangular => {}
+        }
+        if let Aperture::Mask(mask) = &self.aperture {
+            if mask_cdf(mask).last().is_none_or(|&total| total <= 0.0) {
+                return Err(CameraError::EmptyApertureMask);
+            }
+        }
+
+        let view = self.lookfrom - self.lookat;
+        if view.near_zero() {
+            return Err(CameraError::LookfromEqualsLookat);
+        }
+        if self.vup.cross(view.unit_vector()).length() < 1e-6 {
+            return Err(CameraError::VupParallelToView);
+        }
+
+        Ok(())
+    }
+
+    /// Vertical field of view in degrees, derived from the focal length and
+    /// the sensor when set. The sensor is fitted to the image the same way
+    /// most DCC tools do: by width for landscape images and by height otherwise.
+    fn vertical_fov(&self, img_aspect: f32) -> f32 {
+        match self.focal_length {
+            Some(focal_length) => {
+                let sensor_aspect = self.sensor_width / self.sensor_height;
+                let sensor_extent = if img_aspect >= sensor_aspect {
+                    self.sensor_width / img_aspect
+                } else {
+                    self.sensor_height
+                };
+                2.0 * (sensor_extent / (2.0 * focal_length)).atan().to_degrees()
+            }
+            None => self.fov,
+        }
+    }
+
+    fn defocus_radius(&self) -> f32 {
+        match (self.focal_length, self.f_stop) {
+            (Some(focal_length), Some(f_stop)) => {
+                let aperture_diameter_mm = focal_length / f_stop;
+                aperture_diameter_mm / 2.0 / 1000.0 * self.units_per_meter
+            }
+            _ => self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan(),
+        }
+    }
+
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        self.validate()?;
+
         let img_height: u32 = (self.img_width as f32 / self.aspect_ratio) as u32;
 
         let center = self.lookfrom;
+        let fov = self.vertical_fov(self.img_width as f32 / img_height as f32);
 
-        let theta = (self.fov as f32).to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height: f32 = 2.0 * h * self.focus_dist;
+        let viewport_height: f32 = match self.projection {
+            Projection::Orthographic { view_height } => view_height,
+            _ => {
+                let theta = fov.to_radians();
+                let h = (theta / 2.0).tan();
+                2.0 * h * self.focus_dist
+            }
+        };
         let viewport_width: f32 = viewport_height * (self.img_width as f32 / img_height as f32);
 
         let w = (self.lookfrom - self.lookat).unit_vector();
@@ -130,26 +528,43 @@ impl CameraBuilder {
         let pixel_delta_u = viewport_u / self.img_width as f32;
         let pixel_delta_v = viewport_v / img_height as f32;
 
-        let viewport_upper_left =
-            center - (self.focus_dist * w) - viewport_u / 2.0 - viewport_v / 2.0;
+        let viewport_center = match self.projection {
+            Projection::Orthographic { .. } => center,
+            _ => center - (self.focus_dist * w),
+        };
+        let viewport_upper_left = viewport_center - viewport_u / 2.0 - viewport_v / 2.0;
 
-        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_u);
+        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
 
-        let defocus_radius = self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
+        let defocus_radius = self.defocus_radius();
         let defocus_disk_u = u * defocus_radius;
         let defocus_disk_v = v * defocus_radius;
+        let aperture_cdf = match &self.aperture {
+            Aperture::Mask(mask) => mask_cdf(mask),
+            _ => Vec::new(),
+        };
 
-        Camera {
+        Ok(Camera {
             aspect_ratio: self.aspect_ratio,
             img_width: self.img_width,
             samples_per_pixel: self.samples_per_pixel,
             max_depth: self.max_depth,
-            fov: self.fov,
-            lookfrom: self.lookat,
+            fov,
+            lookfrom: self.lookfrom,
             lookat: self.lookat,
             vup: self.vup,
             defocus_angle: self.defocus_angle,
             focus_dist: self.focus_dist,
+            focal_length: self.focal_length,
+            sensor_width: self.sensor_width,
+            sensor_height: self.sensor_height,
+            f_stop: self.f_stop,
+            units_per_meter: self.units_per_meter,
+            aperture: self.aperture.clone(),
+            projection: self.projection,
+            spectral: self.spectral,
+            sky: self.sky,
+            aperture_cdf,
             img_height,
             center,
             pixel00_loc,
@@ -158,120 +573,696 @@ impl CameraBuilder {
             u,
             v,
             w,
+            defocus_radius,
             defocus_disk_u,
             defocus_disk_v,
+        })
+    }
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Name of the light group layer holding the light of emissive surfaces and
+/// the sky, which belongs to no light group.
+pub const EMISSION_LAYER: &str = "emission";
+
+/// Light groups of the scene and the light each has added along a path.
+struct PathLights<'a> {
+    groups: &'a [LightGroup],
+    contributions: Vec<Vec3>,
+}
+
+impl<'a> PathLights<'a> {
+    fn new(groups: &'a [LightGroup]) -> PathLights<'a> {
+        PathLights {
+            groups,
+            contributions: vec![Vec3::zero(); groups.len()],
         }
     }
 }
 
-fn linear_to_gamma(linear: f32) -> f32 {
-    linear.sqrt()
+struct Pixel {
+    color: Vec3,
+    albedo: Vec3,
+    normal: Vec3,
+    light_groups: Vec<Vec3>,
 }
 
 impl Camera {
-    fn ray_color(ray: &Ray, depth: u32, world: &Vec<Box<dyn Hittable>>) -> Vec3 {
-        if depth <= 0 {
+    fn sky_color(&self, ray: &Ray) -> Vec3 {
+        if let Some(sky) = &self.sky {
+            return sky.radiance(ray.direction);
+        }
+
+        let unit_direction = ray.direction.unit_vector();
+        let a: f32 = 0.5 * (unit_direction.y() + 1.0);
+
+        (1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0)
+    }
+
+    /// Carries the wavelength of a dispersed path over to the scattered ray.
+    fn continue_path(ray_in: &Ray, mut scattered: Ray) -> Ray {
+        if scattered.wavelength.is_none() {
+            scattered.wavelength = ray_in.wavelength;
+        }
+        scattered
+    }
+
+    /// In spectral mode, reduces an RGB quantity to its value at the wavelength
+    /// of the path, stored in every channel.
+    fn spectral_value(&self, rgb: Vec3, ray: &Ray) -> Vec3 {
+        match (self.spectral, ray.wavelength) {
+            (true, Some(lambda)) => {
+                let value = spectrum::rgb_to_spectrum(rgb, lambda);
+                Vec3::new(value, value, value)
+            }
+            _ => rgb,
+        }
+    }
+
+    /// Light from `lights` reflected towards the ray at `hit_rec`, each light
+    /// sampled once through a shadow ray.
+    fn direct_light(
+        &self,
+        ray: &Ray,
+        hit_rec: &HitRecord,
+        world: &dyn Hittable,
+        lights: &[Light],
+    ) -> Vec3 {
+        let mut direct = Vec3::zero();
+        for light in lights {
+            let Some(sample) = light.sample(hit_rec.point) else {
+                continue;
+            };
+            let bsdf = hit_rec.material.eval(ray, hit_rec, sample.direction);
+            if bsdf.near_zero() {
+                continue;
+            }
+
+            let shadow_ray = Ray::new(hit_rec.point, sample.direction);
+            let visibility =
+                world.transmittance(&shadow_ray, Interval::new(0.001, sample.distance));
+            direct = direct + visibility * bsdf * sample.radiance;
+        }
+
+        self.spectral_value(direct, ray)
+    }
+
+    /// Direct light from every group, adding each group's share, weighted by
+    /// the `throughput` of the path so far, to its layer.
+    fn group_light(
+        &self,
+        ray: &Ray,
+        hit_rec: &HitRecord,
+        world: &dyn Hittable,
+        lights: &mut PathLights,
+        throughput: Vec3,
+    ) -> Vec3 {
+        let mut direct = Vec3::zero();
+        for (group, contribution) in lights.groups.iter().zip(lights.contributions.iter_mut()) {
+            let light = self.direct_light(ray, hit_rec, world, &group.lights);
+            *contribution = *contribution + throughput * light;
+            direct = direct + light;
+        }
+        direct
+    }
+
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: u32,
+        world: &dyn Hittable,
+        lights: &mut PathLights,
+        throughput: Vec3,
+    ) -> Vec3 {
+        if depth == 0 {
             Vec3::zero()
         } else {
             match world.hit(ray, Interval::new(0.001, f32::INFINITY)) {
-                Some(hit_rec) => match hit_rec.material.scatter(&ray, &hit_rec) {
-                    Some(scattered) => {
-                        scattered.attenuation * Camera::ray_color(&scattered.ray, depth - 1, world)
+                Some(hit_rec) => {
+                    let emitted = self.spectral_value(hit_rec.material.emitted(&hit_rec), ray)
+                        + self.group_light(ray, &hit_rec, world, lights, throughput);
+                    match hit_rec.material.scatter(ray, &hit_rec) {
+                        Some(scattered) => {
+                            let attenuation = self.spectral_value(scattered.attenuation, ray);
+                            let next = Camera::continue_path(ray, scattered.ray);
+                            emitted
+                                + attenuation
+                                    * self.ray_color(
+                                        &next,
+                                        depth - 1,
+                                        world,
+                                        lights,
+                                        throughput * attenuation,
+                                    )
+                        }
+                        None => emitted,
                     }
-                    None => Vec3::zero(),
-                },
-                None => {
-                    let unit_direction = ray.direction.unit_vector();
-                    let a: f32 = 0.5 * (unit_direction.y() + 1.0);
+                }
+                None => self.spectral_value(self.sky_color(ray), ray),
+            }
+        }
+    }
+
+    /// Traces a camera ray and returns its color together with the albedo and
+    /// normal of the first surface hit, which guide the denoiser.
+    fn trace(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &mut PathLights,
+    ) -> (Vec3, Vec3, Vec3) {
+        if self.max_depth == 0 {
+            return (Vec3::zero(), Vec3::zero(), Vec3::zero());
+        }
 
-                    (1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0)
+        let one = Vec3::new(1.0, 1.0, 1.0);
+        match world.hit(ray, Interval::new(0.001, f32::INFINITY)) {
+            Some(hit_rec) => {
+                let emitted = hit_rec.material.emitted(&hit_rec);
+                let direct = self.group_light(ray, &hit_rec, world, lights, one);
+                match hit_rec.material.scatter(ray, &hit_rec) {
+                    Some(scattered) => {
+                        let attenuation = self.spectral_value(scattered.attenuation, ray);
+                        let next = Camera::continue_path(ray, scattered.ray);
+                        let color = self.spectral_value(emitted, ray)
+                            + direct
+                            + attenuation
+                                * self.ray_color(
+                                    &next,
+                                    self.max_depth - 1,
+                                    world,
+                                    lights,
+                                    attenuation,
+                                );
+                        (color, hit_rec.material.albedo(&hit_rec), hit_rec.normal)
+                    }
+                    None => (
+                        self.spectral_value(emitted, ray) + direct,
+                        emitted,
+                        hit_rec.normal,
+                    ),
                 }
             }
+            None => {
+                let color = self.sky_color(ray);
+                (self.spectral_value(color, ray), color, Vec3::zero())
+            }
         }
     }
 
     fn defocus_disk_sample(&self) -> Vec3 {
-        let p = Vec3::random_in_unit_disk();
-        self.center + p.x() * self.defocus_disk_u + p.y() * self.defocus_disk_v
+        let (x, y) = self.aperture.sample(&self.aperture_cdf);
+        self.center + x * self.defocus_disk_u + y * self.defocus_disk_v
     }
 
-    fn write_color(&self, mut file: &File, color: Vec3) {
+    /// Returns a jittered sample position inside pixel `(i, j)` in normalized
+    /// image coordinates, with `(0, 0)` at the upper left and `(1, 1)` at the
+    /// lower right corner.
+    fn pixel_sample_uv(&self, i: u32, j: u32) -> (f32, f32) {
+        let s = (i as f32 + rng::random()) / self.img_width as f32;
+        let t = (j as f32 + rng::random()) / self.img_height as f32;
+        (s, t)
+    }
+
+    fn direction_from_camera(&self, x: f32, y: f32, z: f32) -> Vec3 {
+        x * self.u + y * self.v - z * self.w
+    }
+
+    fn viewport_point(&self, s: f32, t: f32) -> Vec3 {
+        self.pixel00_loc
+            + (s * self.img_width as f32 - 0.5) * self.pixel_delta_u
+            + (t * self.img_height as f32 - 0.5) * self.pixel_delta_v
+    }
+
+    fn get_ray(&self, i: u32, j: u32) -> Ray {
+        let (s, t) = self.pixel_sample_uv(i, j);
+        self.ray_at(s, t)
+    }
+
+    /// Generates a camera ray through normalized image coordinates `(s, t)`.
+    fn ray_at(&self, s: f32, t: f32) -> Ray {
+        match self.projection {
+            Projection::Perspective => {
+                let pixel_sample = self.viewport_point(s, t);
+
+                let ray_origin = if self.defocus_radius <= 0.0 {
+                    self.center
+                } else {
+                    self.defocus_disk_sample()
+                };
+                let ray_direction = pixel_sample - ray_origin;
+
+                Ray::new(ray_origin, ray_direction)
+            }
+            Projection::Orthographic { .. } => {
+                let pixel_sample = self.viewport_point(s, t);
+
+                Ray::new(pixel_sample, -self.w)
+            }
+            Projection::Fisheye => {
+                let x = 2.0 * s - 1.0;
+                let y = (1.0 - 2.0 * t) * self.img_height as f32 / self.img_width as f32;
+
+                let r = (x * x + y * y).sqrt();
+                let theta = r * self.fov.to_radians() / 2.0;
+                let phi = y.atan2(x);
+
+                let direction = self.direction_from_camera(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+
+                Ray::new(self.center, direction)
+            }
+            Projection::Equirectangular => {
+                let phi = (2.0 * s - 1.0) * PI;
+                let theta = (0.5 - t) * PI;
+
+                let direction = self.direction_from_camera(
+                    theta.cos() * phi.sin(),
+                    theta.sin(),
+                    theta.cos() * phi.cos(),
+                );
+
+                Ray::new(self.center, direction)
+            }
+        }
+    }
+
+    fn pixel_color(&self, world: &dyn Hittable, groups: &[LightGroup], i: u32, j: u32) -> Pixel {
+        let mut pixel = Pixel {
+            color: Vec3::zero(),
+            albedo: Vec3::zero(),
+            normal: Vec3::zero(),
+            light_groups: vec![Vec3::zero(); groups.len()],
+        };
+
+        for k in 0..self.samples_per_pixel {
+            let mut ray = self.get_ray(i, j);
+            let mut lights = PathLights::new(groups);
+
+            // In spectral mode, stratify the wavelengths over the samples of
+            // the pixel and accumulate the spectral samples in XYZ.
+            let lambda = self.spectral.then(|| {
+                spectrum::LAMBDA_MIN
+                    + (spectrum::LAMBDA_MAX - spectrum::LAMBDA_MIN) * (k as f32 + rng::random())
+                        / self.samples_per_pixel as f32
+            });
+            ray.wavelength = lambda;
+            let to_xyz = |value: Vec3| match lambda {
+                Some(lambda) => value.x() * spectrum::cie_xyz(lambda),
+                None => value,
+            };
+
+            let (color, albedo, normal) = self.trace(&ray, world, &mut lights);
+
+            pixel.color = pixel.color + to_xyz(color);
+            pixel.albedo = pixel.albedo + albedo;
+            pixel.normal = pixel.normal + normal;
+            for (sum, value) in pixel.light_groups.iter_mut().zip(lights.contributions) {
+                *sum = *sum + to_xyz(value);
+            }
+        }
+
         let scale = 1.0 / self.samples_per_pixel as f32;
+        let resolve = |value: Vec3| {
+            if self.spectral {
+                spectrum::xyz_to_rgb(value) * scale
+            } else {
+                value * scale
+            }
+        };
 
-        let r = linear_to_gamma(color.x() * scale);
-        let g = linear_to_gamma(color.y() * scale);
-        let b = linear_to_gamma(color.z() * scale);
+        Pixel {
+            color: resolve(pixel.color),
+            albedo: pixel.albedo * scale,
+            normal: pixel.normal * scale,
+            light_groups: pixel.light_groups.into_iter().map(resolve).collect(),
+        }
+    }
 
-        let intensity = Interval::new(0.0, 0.999);
+    pub fn render(&self, world: &dyn Hittable) -> FrameBuffer {
+        self.render_with_lights(world, &[])
+    }
 
-        let color_str = format!(
-            "{} {} {}\n",
-            (256.0 * intensity.clamp(r)) as u32,
-            (256.0 * intensity.clamp(g)) as u32,
-            (256.0 * intensity.clamp(b)) as u32
-        );
+    /// Renders `world` lit by the scene's light list in addition to its
+    /// emissive surfaces and the sky.
+    pub fn render_with_lights(&self, world: &dyn Hittable, lights: &[Light]) -> FrameBuffer {
+        let group = LightGroup {
+            name: String::new(),
+            lights: lights.to_vec(),
+        };
+        self.render_groups(world, std::slice::from_ref(&group), false)
+    }
 
-        file.write(color_str.as_bytes())
-            .expect("Unable to write to file");
+    /// Renders `world` lit by the scene's light groups in addition to its
+    /// emissive surfaces and the sky. The light of each group is also written
+    /// to its own layer in `FrameBuffer::light_groups`, followed by an
+    /// `EMISSION_LAYER` with the rest of the light, so the layers add up to
+    /// the color.
+    pub fn render_with_light_groups(
+        &self,
+        world: &dyn Hittable,
+        groups: &[LightGroup],
+    ) -> FrameBuffer {
+        self.render_groups(world, groups, true)
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
-        let px = -0.5 + rng::random();
-        let py = -0.5 + rng::random();
-        return (px * self.pixel_delta_u) + (py * self.pixel_delta_v);
+    fn render_groups(
+        &self,
+        world: &dyn Hittable,
+        groups: &[LightGroup],
+        layers: bool,
+    ) -> FrameBuffer {
+        let mut fb = FrameBuffer::new(self.img_width, self.img_height);
+        if layers {
+            fb.light_groups = groups
+                .iter()
+                .map(|group| group.name.as_str())
+                .chain([EMISSION_LAYER])
+                .map(|name| (name.to_string(), vec![Vec3::zero(); fb.color.len()]))
+                .collect();
+        }
+
+        let pixels: Vec<Pixel> = (0..self.img_height)
+            .into_par_iter()
+            .flat_map_iter(|j| {
+                (0..self.img_width).map(move |i| self.pixel_color(world, groups, i, j))
+            })
+            .collect();
+
+        for (idx, pixel) in pixels.into_iter().enumerate() {
+            fb.color[idx] = pixel.color;
+            fb.albedo[idx] = pixel.albedo;
+            fb.normal[idx] = pixel.normal;
+            if layers {
+                let emission = pixel
+                    .light_groups
+                    .iter()
+                    .fold(pixel.color, |rest, value| rest - *value);
+                let values = pixel.light_groups.into_iter().chain([emission]);
+                for ((_, layer), value) in fb.light_groups.iter_mut().zip(values) {
+                    layer[idx] = value;
+                }
+            }
+        }
+
+        fb
     }
+}
 
-    fn get_ray(&self, i: u32, j: u32) -> Ray {
-        let pixel_center =
-            self.pixel00_loc + (i as f32 * self.pixel_delta_u) + (j as f32 * self.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::material::{Lambertian, Material};
+    use crate::sphere::Sphere;
+    use crate::testing::material;
 
-        let ray_origin = if self.defocus_angle < 0.0 {
-            self.center
-        } else {
-            self.defocus_disk_sample()
+    #[test]
+    fn test_build_rejects_degenerate_input() {
+        let builder = CameraBuilder::new();
+
+        assert!(builder.clone().build().is_ok());
+        assert_eq!(
+            builder.clone().img_width(0).build().err(),
+            Some(CameraError::ZeroWidth)
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .img_width(10)
+                .aspect_ratio(20.0)
+                .build()
+                .err(),
+            Some(CameraError::ZeroHeight {
+                img_width: 10,
+                aspect_ratio: 20.0
+            })
+        );
+        assert_eq!(
+            builder.clone().vup(Vec3::new(0.0, 0.0, 2.0)).build().err(),
+            Some(CameraError::VupParallelToView)
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .lookat(Vec3::new(0.0, 0.0, -1.0))
+                .build()
+                .err(),
+            Some(CameraError::LookfromEqualsLookat)
+        );
+        assert_eq!(
+            builder.fov(180.0).build().err(),
+            Some(CameraError::InvalidFov(180.0))
+        );
+    }
+
+    #[test]
+    fn test_projection_ray_directions() {
+        let camera = |projection: Projection| {
+            CameraBuilder::new()
+                .img_width(101)
+                .aspect_ratio(1.0)
+                .fov(180.0)
+                .projection(projection)
+                .build()
+                .expect("Unable to build camera")
+        };
+        let assert_direction = |ray: Ray, expected: Vec3| {
+            assert!((ray.direction.unit_vector() - expected).length() < 1e-5);
         };
-        let ray_direction = pixel_sample - ray_origin;
 
-        Ray::new(ray_origin, ray_direction)
+        let orthographic = camera(Projection::Orthographic { view_height: 2.0 });
+        let ray = orthographic.ray_at(0.5, 0.5);
+        assert!((ray.origin - orthographic.center).length() < 1e-5);
+        assert_direction(ray, -orthographic.w);
+
+        let fisheye = camera(Projection::Fisheye);
+        assert_direction(fisheye.ray_at(0.5, 0.5), -fisheye.w);
+        assert_direction(fisheye.ray_at(1.0, 0.5), fisheye.u);
+
+        let equirectangular = camera(Projection::Equirectangular);
+        assert_direction(equirectangular.ray_at(0.5, 0.5), -equirectangular.w);
+        assert_direction(equirectangular.ray_at(0.75, 0.5), equirectangular.u);
+        assert_direction(equirectangular.ray_at(0.5, 0.0), equirectangular.v);
     }
 
-    fn pixel_color(&self, world: &Vec<Box<dyn Hittable>>, i: u32, j: u32) -> Vec3 {
-        let mut pixel_color = Vec3::new(0.0, 0.0, 0.0);
+    #[test]
+    fn test_physical_camera() {
+        let builder = CameraBuilder::new().focal_length(50.0).f_stop(2.0);
+
+        let expected = 2.0 * (24.0f32 / 100.0).atan().to_degrees();
+        assert!((builder.vertical_fov(1.0) - expected).abs() < 1e-4);
+        let expected = 2.0 * (36.0 / (16.0 / 9.0) / 100.0f32).atan().to_degrees();
+        assert!((builder.vertical_fov(16.0 / 9.0) - expected).abs() < 1e-4);
+
+        assert!((builder.clone().units_per_meter(100.0).defocus_radius() - 1.25).abs() < 1e-5);
+
+        let builder = CameraBuilder::new().focus_dist(10.0).defocus_angle(2.0);
+        assert_eq!(builder.vertical_fov(1.0), builder.fov);
+        assert!((builder.defocus_radius() - 10.0 * 1.0f32.to_radians().tan()).abs() < 1e-5);
+    }
 
-        for _ in 0..self.samples_per_pixel {
-            let ray = self.get_ray(i, j);
-            pixel_color = pixel_color + Self::ray_color(&ray, self.max_depth, world);
+    #[test]
+    fn test_aperture_mask() {
+        let black = Vec3::zero();
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let builder = |pixels: Vec<Vec3>| {
+            let size = (pixels.len() as f32).sqrt() as u32;
+            let mask = Image {
+                width: size,
+                height: size,
+                pixels,
+            };
+            CameraBuilder::new().aperture(Aperture::Mask(Arc::new(mask)))
+        };
+
+        let empty = builder(Vec::new()).build().err();
+        assert_eq!(empty, Some(CameraError::EmptyApertureMask));
+        let dark = builder(vec![black; 4]).build().err();
+        assert_eq!(dark, Some(CameraError::EmptyApertureMask));
+        let mut corner = vec![black; 16];
+        corner[0] = white;
+        let outside = builder(corner).build().err();
+        assert_eq!(outside, Some(CameraError::EmptyApertureMask));
+
+        let camera = builder(vec![white, black, black, black])
+            .build()
+            .expect("Unable to build camera");
+        for _ in 0..256 {
+            let (x, y) = camera.aperture.sample(&camera.aperture_cdf);
+            assert!((-1.0..=0.0).contains(&x) && (0.0..=1.0).contains(&y));
         }
+    }
+
+    #[test]
+    fn test_autofocus() {
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            1.0,
+            material(),
+        ))];
+
+        let builder = CameraBuilder::new()
+            .lookfrom(Vec3::new(0.0, 0.0, 0.0))
+            .lookat(Vec3::new(0.0, 0.0, -1.0))
+            .img_width(101)
+            .autofocus(FocusTarget::Lookat, &world);
+        assert!((builder.focus_dist - 4.0).abs() < 1e-4);
+
+        let builder = builder
+            .focus_dist(10.0)
+            .autofocus(FocusTarget::Pixel(50, 50), &world);
+        assert!((builder.focus_dist - 4.0).abs() < 1e-3);
 
-        pixel_color
+        let builder = builder
+            .focus_dist(10.0)
+            .autofocus(FocusTarget::Pixel(0, 0), &world);
+        assert_eq!(builder.focus_dist, 10.0);
     }
 
-    pub fn render(&self, world: &Vec<Box<dyn Hittable>>) {
-        let img_format = "P3";
-        let max_colors: u32 = 255;
+    #[test]
+    fn test_direct_light_shadow_ray() {
+        let floor = material();
+        let floor: Box<dyn Hittable> =
+            Box::new(Sphere::new(Vec3::new(0.0, -100.0, 0.0), 100.0, floor));
+        let light = Light::Point(PointLight::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            4.0,
+        ));
+        let camera = CameraBuilder::new().build().unwrap();
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
 
-        let mut file = File::create("img.ppm").expect("Unable to create file");
+        let world = vec![floor];
+        let hit_rec = world
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        let direct = camera.direct_light(&ray, &hit_rec, &world, &[light]);
+        assert!((direct.x() - 0.5 / PI).abs() < 1e-3);
 
-        write!(
-            file,
-            "{}\n{} {}\n{}\n",
-            img_format, self.img_width, self.img_height, max_colors
-        )
-        .expect("Unable to write to file");
+        let occluder: Box<dyn Hittable> = Box::new(Sphere::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            0.2,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::zero(),
+            }),
+        ));
+        let world = vec![world.into_iter().next().unwrap(), occluder];
+        let hit_rec = world
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        let direct = camera.direct_light(&ray, &hit_rec, &world, &[light]);
+        assert_eq!(direct, Vec3::zero());
+    }
 
-        let img: Vec<Vec3> = (0..self.img_height)
-            .collect::<Vec<u32>>()
-            .par_iter()
-            .flat_map(|j| {
-                (0..self.img_width)
-                    .collect::<Vec<u32>>()
-                    .iter()
-                    .map(|i| self.pixel_color(&world, *i, *j))
-                    .collect::<Vec<Vec3>>()
-            })
-            .collect();
+    #[test]
+    fn test_light_groups_sum_to_direct_light() {
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            1.0,
+            material(),
+        ))];
+        let point = |x: f32| {
+            Light::Point(PointLight::new(
+                Vec3::new(x, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 1.0),
+                10.0,
+            ))
+        };
+        let groups = [
+            LightGroup::new("key").light(point(2.0)),
+            LightGroup::new("fill")
+                .light(point(-2.0))
+                .light(point(-3.0)),
+        ];
+
+        // With a single bounce, all light reaching the sphere comes from the
+        // groups.
+        let camera = CameraBuilder::new()
+            .lookfrom(Vec3::new(0.0, 0.0, 0.0))
+            .lookat(Vec3::new(0.0, 0.0, -1.0))
+            .img_width(8)
+            .fov(10.0)
+            .max_depth(1)
+            .build()
+            .unwrap();
+        let fb = camera.render_with_light_groups(&world, &groups);
+
+        let key = fb.light_group("key").unwrap();
+        let fill = fb.light_group("fill").unwrap();
+        let emission = fb.light_group(EMISSION_LAYER).unwrap();
+        assert!(fb.light_group("rim").is_none());
+        let center = fb.index(4, 4);
+        assert!(key[center].x() > 0.0 && fill[center].x() > 0.0);
+        for idx in 0..fb.color.len() {
+            assert!(emission[idx].length() < 1e-5);
+            assert!((fb.color[idx] - (key[idx] + fill[idx])).length() < 1e-5);
+        }
+
+        // Ungrouped lights light the image the same way, without layers.
+        let lights: Vec<Light> = groups.iter().flat_map(|g| g.lights.clone()).collect();
+        let fb = camera.render_with_lights(&world, &lights);
+        assert!(fb.light_groups.is_empty());
+        assert!((fb.color[center] - (key[center] + fill[center])).length() < 0.05);
+    }
+
+    #[test]
+    fn test_emission_layer_holds_sky() {
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            1.0,
+            material(),
+        ))];
+        let groups = [LightGroup::new("key").light(Light::Point(PointLight::new(
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            10.0,
+        )))];
+        let camera = CameraBuilder::new()
+            .lookfrom(Vec3::new(0.0, 0.0, 0.0))
+            .lookat(Vec3::new(0.0, 0.0, -1.0))
+            .img_width(8)
+            .max_depth(4)
+            .build()
+            .unwrap();
+        let fb = camera.render_with_light_groups(&world, &groups);
+
+        let key = fb.light_group("key").unwrap();
+        let emission = fb.light_group(EMISSION_LAYER).unwrap();
+        // The corner sees the sky only.
+        assert!(key[0].length() < 1e-5);
+        assert!((emission[0] - fb.color[0]).length() < 1e-5);
+        for idx in 0..fb.color.len() {
+            assert!((fb.color[idx] - (key[idx] + emission[idx])).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_spectral_render_matches_rgb() {
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            1.0,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.8, 0.3, 0.1),
+            }),
+        ))];
+        let builder = CameraBuilder::new()
+            .lookfrom(Vec3::new(0.0, 0.0, 0.0))
+            .lookat(Vec3::new(0.0, 0.0, -1.0))
+            .img_width(4)
+            .samples_per_pixel(256);
 
-        img.iter().for_each(|color| self.write_color(&file, *color));
+        let mean = |fb: FrameBuffer| fb.color.iter().fold(Vec3::zero(), |a, c| a + *c) / 16.0;
+        let rgb = mean(builder.clone().build().unwrap().render(&world));
+        let spectral = mean(builder.spectral(true).build().unwrap().render(&world));
+        assert!((rgb - spectral).length() < 0.1);
     }
 }
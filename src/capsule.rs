@@ -0,0 +1,146 @@
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Cylinder around the `y` axis from `base` up to `height` above it, closed
+/// by hemispheres at both ends.
+pub struct Capsule {
+    base: Vec3,
+    radius: f32,
+    height: f32,
+    material: Material,
+}
+
+fn quadratic(a: f32, half_b: f32, c: f32) -> [f32; 2] {
+    let discriminant = half_b * half_b - a * c;
+    if a <= 0.0 || discriminant < 0.0 {
+        return [f32::NAN; 2];
+    }
+    let sqrt_d = discriminant.sqrt();
+    [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a]
+}
+
+impl Capsule {
+    pub fn new(base: Vec3, radius: f32, height: f32, material: Material) -> Capsule {
+        Capsule {
+            base,
+            radius,
+            height,
+            material,
+        }
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let p = point - self.base;
+
+        let axis_point = Vec3::new(0.0, p.y().clamp(0.0, self.height), 0.0);
+        let outward_normal = (p - axis_point) / self.radius;
+
+        let (u, tangent) = hittable::azimuth(p);
+        let v = (p.y() + self.radius) / (self.height + 2.0 * self.radius);
+        let bitangent = outward_normal.cross(tangent);
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.material,
+            t,
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        }
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let o = ray.origin - self.base;
+        let d = ray.direction;
+        let r2 = self.radius * self.radius;
+        let mut candidates = [(f32::NAN, ()); 6];
+
+        let side = quadratic(
+            d.x() * d.x() + d.z() * d.z(),
+            o.x() * d.x() + o.z() * d.z(),
+            o.x() * o.x() + o.z() * o.z() - r2,
+        );
+        let top = o - Vec3::new(0.0, self.height, 0.0);
+        let caps = [o, top].map(|c| quadratic(d.dot(d), c.dot(d), c.dot(c) - r2));
+
+        // Each part only counts on its own section of the axis.
+        let roots = side
+            .into_iter()
+            .filter(|t| (0.0..=self.height).contains(&(o.y() + t * d.y())))
+            .chain(caps[0].into_iter().filter(|t| o.y() + t * d.y() < 0.0))
+            .chain(
+                caps[1]
+                    .into_iter()
+                    .filter(|t| o.y() + t * d.y() > self.height),
+            );
+        for (candidate, t) in candidates.iter_mut().zip(roots) {
+            candidate.0 = t;
+        }
+
+        hittable::closest_hit(&mut candidates, ray_t, |t, _| self.hit_record(ray, t))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::from_points(
+            self.base - r,
+            self.base + r + Vec3::new(0.0, self.height, 0.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_hit() {
+        let capsule = Capsule::new(Vec3::zero(), 1.0, 2.0, material());
+
+        let ray = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_rec = capsule
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 7.0).abs() < 1e-5);
+        assert!((hit_rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-5);
+
+        let hit_rec = capsule
+            .hit(&ray, Interval::new(7.5, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 11.0).abs() < 1e-5);
+        assert!(!hit_rec.front_face);
+
+        let ray = Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_rec = capsule
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 4.0).abs() < 1e-5);
+        assert!((hit_rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = capsule
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.u - 0.25).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+        assert!((hit_rec.tangent - Vec3::new(2.0 * PI, 0.0, 0.0)).length() < 1e-4);
+    }
+}
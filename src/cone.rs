@@ -0,0 +1,175 @@
+use crate::aabb::Aabb;
+use crate::cylinder;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Copy)]
+enum Part {
+    Side,
+    Base,
+}
+
+/// Cone around the `y` axis with its base disk at `base` and its apex `height`
+/// above it.
+pub struct Cone {
+    base: Vec3,
+    radius: f32,
+    height: f32,
+    capped: bool,
+    material: Material,
+}
+
+impl Cone {
+    pub fn new(base: Vec3, radius: f32, height: f32, material: Material) -> Cone {
+        Cone {
+            base,
+            radius,
+            height,
+            capped: true,
+            material,
+        }
+    }
+
+    /// Whether the base is closed by a disk.
+    pub fn capped(mut self, capped: bool) -> Cone {
+        self.capped = capped;
+        self
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32, part: Part) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let p = point - self.base;
+
+        let (outward_normal, (u, v), (tangent, bitangent)) = match part {
+            Part::Side => {
+                let k = self.radius / self.height;
+                let below_apex = self.height - p.y();
+                let normal = Vec3::new(p.x(), k * k * below_apex, p.z());
+                let normal = if normal.near_zero() {
+                    Vec3::new(0.0, 1.0, 0.0)
+                } else {
+                    normal.unit_vector()
+                };
+
+                let (u, dpdu) = hittable::azimuth(p);
+                let dpdv = if below_apex > 1e-6 {
+                    self.height * Vec3::new(-p.x() / below_apex, 1.0, -p.z() / below_apex)
+                } else {
+                    Vec3::new(0.0, self.height, 0.0)
+                };
+                (normal, (u, p.y() / self.height), (dpdu, dpdv))
+            }
+            Part::Base => cylinder::disk_surface(p, self.radius, false),
+        };
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.material,
+            t,
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let o = ray.origin - self.base;
+        let d = ray.direction;
+        let mut candidates = [(f32::NAN, Part::Side); 3];
+
+        // x^2 + z^2 = k^2 (h - y)^2
+        let k2 = (self.radius / self.height).powi(2);
+        let w = self.height - o.y();
+        let a = d.x() * d.x() + d.z() * d.z() - k2 * d.y() * d.y();
+        let half_b = o.x() * d.x() + o.z() * d.z() + k2 * w * d.y();
+        let c = o.x() * o.x() + o.z() * o.z() - k2 * w * w;
+
+        let roots = if a.abs() < 1e-8 {
+            [-c / (2.0 * half_b), f32::NAN]
+        } else {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant < 0.0 {
+                [f32::NAN; 2]
+            } else {
+                let sqrt_d = discriminant.sqrt();
+                [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a]
+            }
+        };
+        for (i, t) in roots.into_iter().enumerate() {
+            // The equation also describes the mirrored cone above the apex.
+            let y = o.y() + t * d.y();
+            if (0.0..=self.height).contains(&y) {
+                candidates[i].0 = t;
+            }
+        }
+
+        if self.capped {
+            candidates[2] = (
+                cylinder::disk_intersection(o, d, 0.0, self.radius),
+                Part::Base,
+            );
+        }
+
+        hittable::closest_hit(&mut candidates, ray_t, |t, part| {
+            self.hit_record(ray, t, part)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, 0.0, self.radius);
+        Aabb::from_points(
+            self.base - r,
+            self.base + r + Vec3::new(0.0, self.height, 0.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_hit() {
+        let cone = Cone::new(Vec3::zero(), 1.0, 1.0, material());
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_rec = cone.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+        assert!((hit_rec.t - 4.5).abs() < 1e-5);
+        let expected = Vec3::new(-1.0, 1.0, 0.0).unit_vector();
+        assert!((hit_rec.normal - expected).length() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = cone.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+        assert!((hit_rec.u - 0.25).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+        assert!((hit_rec.tangent - Vec3::new(PI, 0.0, 0.0)).length() < 1e-4);
+
+        let ray = Ray::new(Vec3::new(0.9, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_rec = cone.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+        assert!((hit_rec.t - 4.9).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit_rec = cone.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+        assert!((hit_rec.t - 5.0).abs() < 1e-5);
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!((hit_rec.u - 0.75).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+        assert_eq!(hit_rec.tangent, Vec3::new(2.0, 0.0, 0.0));
+    }
+}
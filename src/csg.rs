@@ -0,0 +1,188 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Union,
+    Intersection,
+    /// The first operand with the second one cut away.
+    Difference,
+}
+
+impl Operation {
+    fn inside(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            Operation::Union => in_a || in_b,
+            Operation::Intersection => in_a && in_b,
+            Operation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// Boolean combination of two solids.
+///
+/// Both operands must be closed surfaces whose hits report `front_face` when
+/// the ray enters them. The surface of the result is made of the operand hits
+/// where the ray crosses between outside and inside of the combined solid, so
+/// CSG nodes can be nested.
+pub struct Csg {
+    a: Box<dyn Hittable>,
+    b: Box<dyn Hittable>,
+    operation: Operation,
+}
+
+impl Csg {
+    pub fn new(operation: Operation, a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+        Csg { a, b, operation }
+    }
+
+    pub fn union(a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+        Csg::new(Operation::Union, a, b)
+    }
+
+    pub fn intersection(a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+        Csg::new(Operation::Intersection, a, b)
+    }
+
+    pub fn difference(a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+        Csg::new(Operation::Difference, a, b)
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.hit_all(ray, ray_t).into_iter().next()
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord<'_>> {
+        // Whether the ray starts inside an operand is only known from its
+        // hits, so look past the end of `ray_t` as well.
+        let all = Interval::new(ray_t.min, f32::INFINITY);
+        let hits_a = self.a.hit_all(ray, all);
+        let hits_b = self.b.hit_all(ray, all);
+
+        let mut in_a = hits_a.first().is_some_and(|hit_rec| !hit_rec.front_face);
+        let mut in_b = hits_b.first().is_some_and(|hit_rec| !hit_rec.front_face);
+        let mut inside = self.operation.inside(in_a, in_b);
+
+        let mut hits = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < hits_a.len() || j < hits_b.len() {
+            let from_a = j == hits_b.len() || (i < hits_a.len() && hits_a[i].t <= hits_b[j].t);
+            let mut hit_rec = if from_a {
+                in_a = hits_a[i].front_face;
+                i += 1;
+                hits_a[i - 1]
+            } else {
+                in_b = hits_b[j].front_face;
+                j += 1;
+                hits_b[j - 1]
+            };
+            if hit_rec.t >= ray_t.max {
+                break;
+            }
+
+            // The normal already faces the ray; only the side of the combined
+            // solid can differ from that of the operand, as on the inner
+            // surface of a difference.
+            let now_inside = self.operation.inside(in_a, in_b);
+            if now_inside != inside {
+                inside = now_inside;
+                hit_rec.front_face = inside;
+                hits.push(hit_rec);
+            }
+        }
+
+        hits
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let a = self.a.bounding_box();
+        let b = self.b.bounding_box();
+        match self.operation {
+            Operation::Union => Aabb::enclosing(a, b),
+            Operation::Intersection => {
+                let overlap =
+                    |a: Interval, b: Interval| Interval::new(a.min.max(b.min), a.max.min(b.max));
+                Aabb::new(overlap(a.x, b.x), overlap(a.y, b.y), overlap(a.z, b.z))
+            }
+            Operation::Difference => a,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cuboid::Cuboid;
+    use crate::sphere::Sphere;
+    use crate::testing::material;
+    use crate::vec3::Vec3;
+
+    fn sphere() -> Box<dyn Hittable> {
+        Box::new(Sphere::new(Vec3::zero(), 1.0, material()))
+    }
+
+    /// Box covering the `x > 0` half of the unit sphere.
+    fn half_space() -> Box<dyn Hittable> {
+        Box::new(Cuboid::new(
+            Vec3::new(0.0, -2.0, -2.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            material(),
+        ))
+    }
+
+    fn ts(hits: &[HitRecord]) -> Vec<(f32, bool)> {
+        hits.iter().map(|h| (h.t, h.front_face)).collect()
+    }
+
+    #[test]
+    fn test_sphere_minus_box() {
+        let csg = Csg::difference(sphere(), half_space());
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+
+        // From the cut side, the first surface is the flat face of the box.
+        let ray = Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit_rec = csg.hit(&ray, ray_t).unwrap();
+        assert_eq!(hit_rec.t, 5.0);
+        assert!(hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            ts(&csg.hit_all(&ray, ray_t)),
+            vec![(5.0, true), (6.0, false)]
+        );
+
+        // Inside the removed half nothing is left to hit.
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(csg.hit(&ray, ray_t).is_none());
+
+        // Starting inside the remaining half.
+        let ray = Ray::new(Vec3::new(-0.5, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_rec = csg.hit(&ray, ray_t).unwrap();
+        assert_eq!(hit_rec.t, 0.5);
+        assert!(!hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+
+        let union = Csg::union(sphere(), half_space());
+        assert_eq!(
+            ts(&union.hit_all(&ray, ray_t)),
+            vec![(4.0, true), (7.0, false)]
+        );
+
+        let intersection = Csg::intersection(sphere(), half_space());
+        assert_eq!(
+            ts(&intersection.hit_all(&ray, ray_t)),
+            vec![(5.0, true), (6.0, false)]
+        );
+        let bbox = intersection.bounding_box();
+        assert_eq!(bbox.x, Interval::new(0.0, 1.0));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Solid axis-aligned box between two corners. Each face has its own texture
+/// coordinates running from 0 to 1 across it.
+pub struct Cuboid {
+    min: Vec3,
+    max: Vec3,
+    material: Material,
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3, material: Material) -> Cuboid {
+        let bbox = Aabb::from_points(a, b);
+        Cuboid {
+            min: Vec3::new(bbox.x.min, bbox.y.min, bbox.z.min),
+            max: Vec3::new(bbox.x.max, bbox.y.max, bbox.z.max),
+            material,
+        }
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32, axis: usize) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let center = 0.5 * (self.min + self.max);
+
+        let mut outward_normal = Vec3::zero();
+        outward_normal.e[axis] = if point.e[axis] > center.e[axis] {
+            1.0
+        } else {
+            -1.0
+        };
+
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let size = self.max - self.min;
+        let mut tangent = Vec3::zero();
+        tangent.e[a] = size.e[a];
+        let mut bitangent = Vec3::zero();
+        bitangent.e[b] = size.e[b];
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.material,
+            t,
+            u: (point.e[a] - self.min.e[a]) / size.e[a],
+            v: (point.e[b] - self.min.e[b]) / size.e[b],
+            tangent,
+            bitangent,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        // Slab test, remembering which axis bounds the entry and the exit.
+        let mut near = (f32::NEG_INFINITY, 0);
+        let mut far = (f32::INFINITY, 0);
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction.e[axis];
+            let t0 = (self.min.e[axis] - ray.origin.e[axis]) * inv_d;
+            let t1 = (self.max.e[axis] - ray.origin.e[axis]) * inv_d;
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            if t0 > near.0 {
+                near = (t0, axis);
+            }
+            if t1 < far.0 {
+                far = (t1, axis);
+            }
+        }
+        if near.0 > far.0 {
+            return None;
+        }
+
+        hittable::closest_hit(&mut [near, far], ray_t, |t, axis| {
+            self.hit_record(ray, t, axis)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(self.min, self.max).padded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    #[test]
+    fn test_hit() {
+        let cuboid = Cuboid::new(
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-1.0, -1.0, -1.0),
+            material(),
+        );
+
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = cuboid
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert_eq!(hit_rec.t, 4.0);
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit_rec.front_face);
+        assert!((hit_rec.u - 0.75).abs() < 1e-6);
+        assert!((hit_rec.v - 0.5).abs() < 1e-6);
+
+        let hit_rec = cuboid.hit(&ray, Interval::new(4.5, f32::INFINITY)).unwrap();
+        assert_eq!(hit_rec.t, 6.0);
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(!hit_rec.front_face);
+
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cuboid
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+}
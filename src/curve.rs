@@ -0,0 +1,337 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveShape {
+    /// Thin tube whose shading normal wraps around the curve as if it were
+    /// round, while being intersected as a flat strip facing the ray.
+    Cylinder,
+    /// Flat strip facing `normal`, such as a blade of grass.
+    Ribbon { normal: Vec3 },
+}
+
+/// Cubic Bézier curve with a width varying linearly from one end to the
+/// other, for hair, fur and grass.
+///
+/// Texture coordinate `u` runs along the curve and `v` across it, and the
+/// tangent follows the curve, as the hair BSDF expects.
+pub struct Curve {
+    points: [Vec3; 4],
+    widths: (f32, f32),
+    shape: CurveShape,
+    material: Material,
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    (1.0 - t) * a + t * b
+}
+
+fn blossom(p: &[Vec3; 4], u: f32) -> (Vec3, Vec3) {
+    let a = [0, 1, 2].map(|i| (1.0 - u) * p[i] + u * p[i + 1]);
+    let b = [0, 1].map(|i| (1.0 - u) * a[i] + u * a[i + 1]);
+    let point = (1.0 - u) * b[0] + u * b[1];
+    (point, 3.0 * (b[1] - b[0]))
+}
+
+fn split(p: &[Vec3; 4]) -> [[Vec3; 4]; 2] {
+    let mid = |a: Vec3, b: Vec3| 0.5 * (a + b);
+    let a = [mid(p[0], p[1]), mid(p[1], p[2]), mid(p[2], p[3])];
+    let b = [mid(a[0], a[1]), mid(a[1], a[2])];
+    let c = mid(b[0], b[1]);
+    [[p[0], a[0], b[0], c], [c, b[1], a[2], p[3]]]
+}
+
+/// Converts a uniform cubic B-spline through `points` into Bézier segments,
+/// one for each window of four consecutive points.
+pub fn bspline_segments(points: &[Vec3]) -> Vec<[Vec3; 4]> {
+    points
+        .windows(4)
+        .map(|p| {
+            [
+                (p[0] + 4.0 * p[1] + p[2]) / 6.0,
+                (4.0 * p[1] + 2.0 * p[2]) / 6.0,
+                (2.0 * p[1] + 4.0 * p[2]) / 6.0,
+                (p[1] + 4.0 * p[2] + p[3]) / 6.0,
+            ]
+        })
+        .collect()
+}
+
+/// Closest hit of the recursion, in ray space.
+struct Segment {
+    z: f32,
+    u: f32,
+    v: f32,
+}
+
+impl Curve {
+    pub fn new(
+        points: [Vec3; 4],
+        widths: (f32, f32),
+        shape: CurveShape,
+        material: Material,
+    ) -> Curve {
+        Curve {
+            points,
+            widths,
+            shape,
+            material,
+        }
+    }
+
+    pub fn cylinder(points: [Vec3; 4], widths: (f32, f32), material: Material) -> Curve {
+        Curve::new(points, widths, CurveShape::Cylinder, material)
+    }
+
+    pub fn ribbon(
+        points: [Vec3; 4],
+        widths: (f32, f32),
+        normal: Vec3,
+        material: Material,
+    ) -> Curve {
+        let normal = normal.unit_vector();
+        Curve::new(points, widths, CurveShape::Ribbon { normal }, material)
+    }
+
+    fn width(&self, u: f32) -> f32 {
+        lerp(u, self.widths.0, self.widths.1)
+    }
+
+    /// Recursively subdivides the curve in ray space, where the ray runs down
+    /// the `z` axis, and intersects the leaves as straight segments.
+    #[allow(clippy::too_many_arguments)]
+    fn intersect(
+        &self,
+        cp: &[Vec3; 4],
+        u0: f32,
+        u1: f32,
+        depth: u32,
+        z_min: f32,
+        width_scale: f32,
+        closest: &mut Option<Segment>,
+    ) {
+        let z_max = closest.as_ref().map_or(f32::INFINITY, |hit| hit.z);
+        let half_width = 0.5 * width_scale * self.width(u0).max(self.width(u1));
+        let (min, max) = cp.iter().fold(
+            (
+                Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+                Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), p| {
+                (
+                    Vec3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z())),
+                    Vec3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z())),
+                )
+            },
+        );
+        if min.x() - half_width > 0.0
+            || max.x() + half_width < 0.0
+            || min.y() - half_width > 0.0
+            || max.y() + half_width < 0.0
+            || max.z() + half_width < z_min
+            || min.z() - half_width > z_max
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let um = 0.5 * (u0 + u1);
+            let [a, b] = split(cp);
+            self.intersect(&a, u0, um, depth - 1, z_min, width_scale, closest);
+            self.intersect(&b, um, u1, depth - 1, z_min, width_scale, closest);
+            return;
+        }
+
+        // Only accept points between the perpendiculars at both ends, so
+        // neighboring leaves don't both report the same hit.
+        let edge_start = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        let edge_end = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if edge_start < 0.0 || edge_end < 0.0 {
+            return;
+        }
+
+        let (dx, dy) = (cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y());
+        let denom = dx * dx + dy * dy;
+        if denom == 0.0 {
+            return;
+        }
+        let w = ((-cp[0].x() * dx - cp[0].y() * dy) / denom).clamp(0.0, 1.0);
+        let u = lerp(w, u0, u1);
+        let hit_width = width_scale * self.width(u);
+
+        let (pc, dpcdw) = blossom(cp, w);
+        let distance2 = pc.x() * pc.x() + pc.y() * pc.y();
+        if distance2 > 0.25 * hit_width * hit_width || pc.z() <= z_min || pc.z() >= z_max {
+            return;
+        }
+
+        let distance = distance2.sqrt();
+        let side = dpcdw.x() * -pc.y() + pc.x() * dpcdw.y();
+        let v = if side > 0.0 {
+            0.5 + distance / hit_width
+        } else {
+            0.5 - distance / hit_width
+        };
+        *closest = Some(Segment { z: pc.z(), u, v });
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let length = ray.direction.length();
+        let frame = Onb::from_w(ray.direction);
+        let cp = self.points.map(|p| frame.to_local(p - ray.origin));
+
+        let width_scale = match self.shape {
+            CurveShape::Cylinder => 1.0,
+            CurveShape::Ribbon { normal } => normal.dot(frame.w).abs(),
+        };
+        let max_width = width_scale * self.widths.0.max(self.widths.1);
+        if max_width <= 0.0 {
+            return None;
+        }
+
+        // Subdivide until the segments are within a fraction of the width of
+        // the curve, judging the flatness by the second differences.
+        let l0 = (0..2)
+            .map(|i| {
+                let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+                d.x().abs().max(d.y().abs()).max(d.z().abs())
+            })
+            .fold(0.0, f32::max);
+        let epsilon = 0.05 * max_width;
+        let depth = if l0 > 0.0 {
+            ((6.0 * std::f32::consts::SQRT_2 * l0 / (8.0 * epsilon)).log2() / 2.0)
+                .round()
+                .clamp(0.0, 10.0) as u32
+        } else {
+            0
+        };
+
+        let mut closest = None;
+        self.intersect(
+            &cp,
+            0.0,
+            1.0,
+            depth,
+            ray_t.min * length,
+            width_scale,
+            &mut closest,
+        );
+        let segment = closest?;
+        let t = segment.z / length;
+        if !ray_t.surrounds(t) || !self.material.alpha_test(segment.u, segment.v) {
+            return None;
+        }
+
+        let point = ray.point_at(t);
+        let (center, tangent) = blossom(&self.points, segment.u);
+        let (normal, front_face) = match self.shape {
+            CurveShape::Cylinder => {
+                // Bend the normal from facing the ray at the center of the
+                // strip to sideways at its edges.
+                let facing =
+                    -frame.w - (-frame.w).dot(tangent.unit_vector()) * tangent.unit_vector();
+                let offset = (point - center) / (0.5 * self.width(segment.u));
+                let along = offset.dot(tangent.unit_vector()) * tangent.unit_vector();
+                let lateral = offset - along;
+                let outward = (1.0 - lateral.length_squared()).max(0.0).sqrt();
+                (
+                    (lateral + outward * facing.unit_vector()).unit_vector(),
+                    true,
+                )
+            }
+            CurveShape::Ribbon { normal } => {
+                let front_face = ray.direction.dot(normal) < 0.0;
+                (if front_face { normal } else { -normal }, front_face)
+            }
+        };
+
+        Some(HitRecord {
+            point,
+            normal,
+            material: &self.material,
+            t,
+            u: segment.u,
+            v: segment.v,
+            tangent,
+            bitangent: normal.cross(tangent),
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let half_width = 0.5 * self.widths.0.max(self.widths.1);
+        let pad = Vec3::new(half_width, half_width, half_width);
+        self.points.iter().fold(crate::aabb::EMPTY, |bbox, p| {
+            Aabb::enclosing(bbox, Aabb::from_points(*p - pad, *p + pad))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    fn line() -> [Vec3; 4] {
+        [-1.5, -0.5, 0.5, 1.5].map(|x| Vec3::new(x, 0.0, -5.0))
+    }
+
+    #[test]
+    fn test_cylinder() {
+        let curve = Curve::cylinder(line(), (0.2, 0.2), material());
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = curve.hit(&ray, ray_t).unwrap();
+        assert!((hit_rec.t - 5.0).abs() < 1e-4);
+        assert!((hit_rec.u - 0.5).abs() < 1e-4);
+        assert!((hit_rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4);
+        assert!(hit_rec.tangent.unit_vector().x() > 0.999);
+
+        // Halfway to the edge the normal turns by 30 degrees.
+        let ray = Ray::new(Vec3::new(0.3, 0.05, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = curve.hit(&ray, ray_t).unwrap();
+        let expected = Vec3::new(0.0, 0.5, 0.75f32.sqrt());
+        assert!((hit_rec.normal - expected).length() < 1e-3);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.15, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(curve.hit(&ray, ray_t).is_none());
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(curve.hit(&ray, ray_t).is_none());
+    }
+
+    #[test]
+    fn test_ribbon_and_varying_width() {
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+        let edge_on = Curve::ribbon(line(), (0.2, 0.2), Vec3::new(0.0, 1.0, 0.0), material());
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(edge_on.hit(&ray, ray_t).is_none());
+
+        // Tapering from 0.4 to nothing: wide at the start, gone at the tip.
+        let blade = Curve::ribbon(line(), (0.4, 0.0), Vec3::new(0.0, 0.0, 1.0), material());
+        let ray = Ray::new(Vec3::new(-1.2, 0.15, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = blade.hit(&ray, ray_t).unwrap();
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit_rec.front_face);
+        let ray = Ray::new(Vec3::new(1.2, 0.15, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(blade.hit(&ray, ray_t).is_none());
+    }
+
+    #[test]
+    fn test_bspline_segments() {
+        let points: Vec<Vec3> = (0..5).map(|i| Vec3::new(i as f32, 0.0, 0.0)).collect();
+        let segments = bspline_segments(&points);
+        assert_eq!(segments.len(), 2);
+        assert!((segments[0][0] - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+        assert!((segments[0][3] - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-6);
+        assert_eq!(segments[0][3], segments[1][0]);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::hittable::HitRecord;
+use crate::material::{Material, Scatter, Scatterable};
+use crate::ray::Ray;
+use crate::rng;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Surface is present where alpha is at least the threshold.
+    Threshold(f32),
+    /// Surface is present with probability alpha, which gives soft edges and
+    /// partially transparent surfaces at the cost of noise.
+    Stochastic,
+}
+
+/// Cuts holes into the wrapped material where its opacity is low. The test is
+/// done during intersection, so cut-out parts are invisible to every ray,
+/// including the ones that determine shadows.
+#[derive(Clone)]
+pub struct Cutout {
+    pub material: Material,
+    pub alpha: Texture,
+    pub mode: AlphaMode,
+}
+
+impl Cutout {
+    /// Fraction of rays that find the surface present at `(u, v)`.
+    pub fn opacity(&self, u: f32, v: f32) -> f32 {
+        let alpha = self.alpha.scalar(u, v);
+        match self.mode {
+            AlphaMode::Threshold(threshold) => {
+                if alpha >= threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            AlphaMode::Stochastic => alpha.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn alpha_test(&self, u: f32, v: f32) -> bool {
+        let alpha = self.opacity(u, v);
+        alpha >= 1.0 || (alpha > 0.0 && rng::random() < alpha)
+    }
+}
+
+impl Scatterable for Cutout {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        self.material.scatter(ray_in, hit_rec)
+    }
+
+    fn emitted(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.material.emitted(hit_rec)
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        self.material.eval(ray_in, hit_rec, direction)
+    }
+
+    fn albedo(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.material.albedo(hit_rec)
+    }
+}
@@ -0,0 +1,208 @@
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Copy)]
+enum Part {
+    Side,
+    Bottom,
+    Top,
+}
+
+/// Cylinder around the `y` axis from `base` up to `height` above it. Use an
+/// `Instance` to place it in any other orientation.
+pub struct Cylinder {
+    base: Vec3,
+    radius: f32,
+    height: f32,
+    capped: bool,
+    material: Material,
+}
+
+/// Intersection of a ray in local coordinates with a disk of `radius` around
+/// the `y` axis at height `y`.
+pub(crate) fn disk_intersection(origin: Vec3, direction: Vec3, y: f32, radius: f32) -> f32 {
+    let t = (y - origin.y()) / direction.y();
+    let p = origin + t * direction;
+    if p.x() * p.x() + p.z() * p.z() <= radius * radius {
+        t
+    } else {
+        f32::NAN
+    }
+}
+
+/// Outward normal, texture coordinates and tangents of a point `p` on a disk
+/// cap facing up or down, mapping the disk to the unit square.
+pub(crate) fn disk_surface(p: Vec3, radius: f32, up: bool) -> (Vec3, (f32, f32), (Vec3, Vec3)) {
+    let u = 0.5 + p.x() / (2.0 * radius);
+    if up {
+        let v = 0.5 - p.z() / (2.0 * radius);
+        let tangents = (
+            Vec3::new(2.0 * radius, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -2.0 * radius),
+        );
+        (Vec3::new(0.0, 1.0, 0.0), (u, v), tangents)
+    } else {
+        let v = 0.5 + p.z() / (2.0 * radius);
+        let tangents = (
+            Vec3::new(2.0 * radius, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0 * radius),
+        );
+        (Vec3::new(0.0, -1.0, 0.0), (u, v), tangents)
+    }
+}
+
+impl Cylinder {
+    pub fn new(base: Vec3, radius: f32, height: f32, material: Material) -> Cylinder {
+        Cylinder {
+            base,
+            radius,
+            height,
+            capped: true,
+            material,
+        }
+    }
+
+    /// Whether the ends are closed by disks. Uncapped cylinders are open tubes
+    /// whose inside is visible.
+    pub fn capped(mut self, capped: bool) -> Cylinder {
+        self.capped = capped;
+        self
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32, part: Part) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let p = point - self.base;
+
+        let (outward_normal, (u, v), (tangent, bitangent)) = match part {
+            Part::Side => {
+                let (u, dpdu) = hittable::azimuth(p);
+                let normal = Vec3::new(p.x(), 0.0, p.z()) / self.radius;
+                let dpdv = Vec3::new(0.0, self.height, 0.0);
+                (normal, (u, p.y() / self.height), (dpdu, dpdv))
+            }
+            Part::Bottom => disk_surface(p, self.radius, false),
+            Part::Top => disk_surface(p, self.radius, true),
+        };
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.material,
+            t,
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let o = ray.origin - self.base;
+        let d = ray.direction;
+        let mut candidates = [(f32::NAN, Part::Side); 4];
+
+        let a = d.x() * d.x() + d.z() * d.z();
+        let half_b = o.x() * d.x() + o.z() * d.z();
+        let c = o.x() * o.x() + o.z() * o.z() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a > 0.0 && discriminant >= 0.0 {
+            let sqrt_d = discriminant.sqrt();
+            for (i, t) in [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a]
+                .into_iter()
+                .enumerate()
+            {
+                let y = o.y() + t * d.y();
+                if (0.0..=self.height).contains(&y) {
+                    candidates[i].0 = t;
+                }
+            }
+        }
+
+        if self.capped {
+            candidates[2] = (disk_intersection(o, d, 0.0, self.radius), Part::Bottom);
+            candidates[3] = (disk_intersection(o, d, self.height, self.radius), Part::Top);
+        }
+
+        hittable::closest_hit(&mut candidates, ray_t, |t, part| {
+            self.hit_record(ray, t, part)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, 0.0, self.radius);
+        Aabb::from_points(
+            self.base - r,
+            self.base + r + Vec3::new(0.0, self.height, 0.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+    use std::f32::consts::PI;
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(Vec3::new(0.0, -1.0, 0.0), 1.0, 2.0, material())
+    }
+
+    #[test]
+    fn test_side_and_caps() {
+        let cylinder = cylinder();
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_rec = cylinder
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 4.0).abs() < 1e-5);
+        assert!((hit_rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-5);
+        assert!(hit_rec.front_face);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = cylinder
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.u - 0.25).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+        assert!((hit_rec.tangent - Vec3::new(2.0 * PI, 0.0, 0.0)).length() < 1e-4);
+
+        let ray = Ray::new(Vec3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_rec = cylinder
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 4.0).abs() < 1e-5);
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit_rec.u - 0.75).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+        assert_eq!(hit_rec.tangent, Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_uncapped_inside_is_visible() {
+        let cylinder = cylinder().capped(false);
+        let ray = Ray::new(Vec3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_rec = cylinder
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!(!hit_rec.front_face);
+        assert!((hit_rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-5);
+    }
+}
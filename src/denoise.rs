@@ -0,0 +1,221 @@
+use crate::framebuffer::FrameBuffer;
+use crate::vec3::Vec3;
+use rayon::prelude::*;
+
+/// Joint bilateral filter guided by the albedo and normal AOVs of a render.
+///
+/// Lighting is filtered separately from texture detail: the color is divided by
+/// the albedo before filtering and multiplied back afterwards, so edges and
+/// textures stay sharp while the noisy illumination gets smoothed.
+pub struct Denoiser {
+    pub radius: u32,
+    pub sigma_spatial: f32,
+    pub sigma_color: f32,
+    pub sigma_albedo: f32,
+    pub sigma_normal: f32,
+}
+
+const ALBEDO_EPSILON: f32 = 1e-3;
+
+impl Denoiser {
+    pub fn new() -> Denoiser {
+        Denoiser {
+            radius: 5,
+            sigma_spatial: 3.0,
+            sigma_color: 0.6,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.2,
+        }
+    }
+
+    pub fn radius(mut self, radius: u32) -> Denoiser {
+        self.radius = radius;
+        self
+    }
+
+    pub fn sigma_spatial(mut self, sigma_spatial: f32) -> Denoiser {
+        self.sigma_spatial = sigma_spatial;
+        self
+    }
+
+    pub fn sigma_color(mut self, sigma_color: f32) -> Denoiser {
+        self.sigma_color = sigma_color;
+        self
+    }
+
+    pub fn sigma_albedo(mut self, sigma_albedo: f32) -> Denoiser {
+        self.sigma_albedo = sigma_albedo;
+        self
+    }
+
+    pub fn sigma_normal(mut self, sigma_normal: f32) -> Denoiser {
+        self.sigma_normal = sigma_normal;
+        self
+    }
+
+    fn demodulate(color: Vec3, albedo: Vec3) -> Vec3 {
+        Vec3::new(
+            color.x() / albedo.x().max(ALBEDO_EPSILON),
+            color.y() / albedo.y().max(ALBEDO_EPSILON),
+            color.z() / albedo.z().max(ALBEDO_EPSILON),
+        )
+    }
+
+    fn modulate(irradiance: Vec3, albedo: Vec3) -> Vec3 {
+        Vec3::new(
+            irradiance.x() * albedo.x().max(ALBEDO_EPSILON),
+            irradiance.y() * albedo.y().max(ALBEDO_EPSILON),
+            irradiance.z() * albedo.z().max(ALBEDO_EPSILON),
+        )
+    }
+
+    fn gaussian(distance_squared: f32, sigma: f32) -> f32 {
+        (-distance_squared / (2.0 * sigma * sigma)).exp()
+    }
+
+    fn filter_pixel(&self, fb: &FrameBuffer, irradiance: &[Vec3], i: u32, j: u32) -> Vec3 {
+        let center = fb.index(i, j);
+        let center_irradiance = irradiance[center];
+        let center_albedo = fb.albedo[center];
+        let center_normal = fb.normal[center];
+
+        let r = self.radius as i64;
+        let mut sum = Vec3::zero();
+        let mut weight_sum = 0.0;
+
+        for dy in -r..=r {
+            let y = j as i64 + dy;
+            if y < 0 || y >= fb.height as i64 {
+                continue;
+            }
+
+            for dx in -r..=r {
+                let x = i as i64 + dx;
+                if x < 0 || x >= fb.width as i64 {
+                    continue;
+                }
+
+                let idx = fb.index(x as u32, y as u32);
+                let weight = Self::gaussian((dx * dx + dy * dy) as f32, self.sigma_spatial)
+                    * Self::gaussian(
+                        (irradiance[idx] - center_irradiance).length_squared(),
+                        self.sigma_color,
+                    )
+                    * Self::gaussian(
+                        (fb.albedo[idx] - center_albedo).length_squared(),
+                        self.sigma_albedo,
+                    )
+                    * Self::gaussian(
+                        (fb.normal[idx] - center_normal).length_squared(),
+                        self.sigma_normal,
+                    );
+
+                sum = sum + weight * irradiance[idx];
+                weight_sum += weight;
+            }
+        }
+
+        Self::modulate(sum / weight_sum, center_albedo)
+    }
+
+    /// Filters a color `layer` of `fb`, guided by the albedo and normal of
+    /// the render.
+    fn denoise_layer(&self, fb: &FrameBuffer, layer: &[Vec3]) -> Vec<Vec3> {
+        let irradiance: Vec<Vec3> = layer
+            .iter()
+            .zip(fb.albedo.iter())
+            .map(|(color, albedo)| Self::demodulate(*color, *albedo))
+            .collect();
+
+        (0..fb.height)
+            .into_par_iter()
+            .flat_map_iter(|j| {
+                let irradiance = &irradiance;
+                (0..fb.width).map(move |i| self.filter_pixel(fb, irradiance, i, j))
+            })
+            .collect()
+    }
+
+    /// Denoises the color and every light group layer of `fb` with the same
+    /// guides. Each layer is filtered on its own, so the denoised layers only
+    /// approximately add up to the denoised color.
+    pub fn denoise(&self, fb: &FrameBuffer) -> FrameBuffer {
+        FrameBuffer {
+            width: fb.width,
+            height: fb.height,
+            color: self.denoise_layer(fb, &fb.color),
+            albedo: fb.albedo.clone(),
+            normal: fb.normal.clone(),
+            light_groups: fb
+                .light_groups
+                .iter()
+                .map(|(name, layer)| (name.clone(), self.denoise_layer(fb, layer)))
+                .collect(),
+        }
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_image_is_unchanged() {
+        let mut fb = FrameBuffer::new(8, 8);
+        fb.color = vec![Vec3::new(0.25, 0.5, 0.75); 64];
+        fb.albedo = vec![Vec3::new(0.5, 0.5, 0.5); 64];
+        fb.normal = vec![Vec3::new(0.0, 1.0, 0.0); 64];
+
+        let denoised = Denoiser::new().denoise(&fb);
+        for color in denoised.color {
+            assert!((color - Vec3::new(0.25, 0.5, 0.75)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_noise_is_reduced() {
+        let mut fb = FrameBuffer::new(16, 16);
+        fb.albedo = vec![Vec3::new(1.0, 1.0, 1.0); 256];
+        fb.normal = vec![Vec3::new(0.0, 0.0, 1.0); 256];
+        fb.color = (0..256)
+            .map(|k| {
+                let c = if k % 2 == 0 { 0.3 } else { 0.7 };
+                Vec3::new(c, c, c)
+            })
+            .collect();
+        fb.light_groups = vec![("key".to_string(), fb.color.clone())];
+
+        let denoised = Denoiser::new().denoise(&fb);
+        let idx = denoised.index(8, 8);
+        assert!((denoised.color[idx].x() - 0.5).abs() < 0.1);
+        assert!((denoised.light_group("key").unwrap()[idx].x() - 0.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_normal_edges_are_preserved() {
+        let mut fb = FrameBuffer::new(16, 16);
+        fb.albedo = vec![Vec3::new(1.0, 1.0, 1.0); 256];
+        for j in 0..16 {
+            for i in 0..16 {
+                let idx = fb.index(i, j);
+                if i < 8 {
+                    fb.color[idx] = Vec3::new(0.1, 0.1, 0.1);
+                    fb.normal[idx] = Vec3::new(0.0, 1.0, 0.0);
+                } else {
+                    fb.color[idx] = Vec3::new(0.9, 0.9, 0.9);
+                    fb.normal[idx] = Vec3::new(1.0, 0.0, 0.0);
+                }
+            }
+        }
+
+        let denoised = Denoiser::new().denoise(&fb);
+        assert!(denoised.color[denoised.index(7, 8)].x() < 0.15);
+        assert!(denoised.color[denoised.index(8, 8)].x() > 0.85);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::interval::Interval;
+use crate::vec3::Vec3;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+pub struct FrameBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub color: Vec<Vec3>,
+    pub albedo: Vec<Vec3>,
+    pub normal: Vec<Vec3>,
+    /// Light of each named light group, in the order the groups were given.
+    pub light_groups: Vec<(String, Vec<Vec3>)>,
+}
+
+fn linear_to_gamma(linear: f32) -> f32 {
+    linear.sqrt()
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> FrameBuffer {
+        let size = (width * height) as usize;
+
+        FrameBuffer {
+            width,
+            height,
+            color: vec![Vec3::zero(); size],
+            albedo: vec![Vec3::zero(); size],
+            normal: vec![Vec3::zero(); size],
+            light_groups: Vec::new(),
+        }
+    }
+
+    pub fn index(&self, i: u32, j: u32) -> usize {
+        (j * self.width + i) as usize
+    }
+
+    pub fn light_group(&self, name: &str) -> Option<&[Vec3]> {
+        self.light_groups
+            .iter()
+            .find(|(group, _)| group == name)
+            .map(|(_, layer)| layer.as_slice())
+    }
+
+    pub fn write_ppm(&self, path: &str) -> io::Result<()> {
+        self.write_layer_ppm(path, &self.color)
+    }
+
+    pub fn write_layer_ppm(&self, path: &str, layer: &[Vec3]) -> io::Result<()> {
+        let img_format = "P3";
+        let max_colors: u32 = 255;
+
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        write!(
+            writer,
+            "{}\n{} {}\n{}\n",
+            img_format, self.width, self.height, max_colors
+        )?;
+
+        let intensity = Interval::new(0.0, 0.999);
+
+        for color in layer {
+            let r = linear_to_gamma(color.x());
+            let g = linear_to_gamma(color.y());
+            let b = linear_to_gamma(color.z());
+
+            writeln!(
+                writer,
+                "{} {} {}",
+                (256.0 * intensity.clamp(r)) as u32,
+                (256.0 * intensity.clamp(g)) as u32,
+                (256.0 * intensity.clamp(b)) as u32
+            )?;
+        }
+
+        writer.flush()
+    }
+}
@@ -0,0 +1,536 @@
+use crate::bump::{Bump, BumpMap};
+use crate::camera::{CameraBuilder, Projection};
+use crate::cutout::{AlphaMode, Cutout};
+use crate::hittable::Hittable;
+use crate::image::Image;
+use crate::instance::Instance;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::material::Material;
+use crate::mesh::{Mesh, MeshData};
+use crate::principled::Principled;
+use crate::sphere::Sphere;
+use crate::texture::Texture;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+use std::io::{Error, ErrorKind, Result};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// Light from the `KHR_lights_punctual` extension in world space. Angles are
+/// in radians and `intensity` keeps the extension's photometric units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PunctualLight {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub range: Option<f32>,
+}
+
+impl PunctualLight {
+    /// Equivalent light for the renderer's light list, with the intensity
+    /// used as is and `range` ignored.
+    pub fn light(&self) -> Light {
+        match self.kind {
+            LightKind::Directional => Light::Directional(DirectionalLight::new(
+                self.direction,
+                self.color,
+                self.intensity,
+            )),
+            LightKind::Point => {
+                Light::Point(PointLight::new(self.position, self.color, self.intensity))
+            }
+            LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => Light::Spot(
+                SpotLight::new(self.position, self.direction, self.color, self.intensity).cone(
+                    outer_cone_angle.to_degrees(),
+                    (outer_cone_angle - inner_cone_angle).to_degrees(),
+                ),
+            ),
+        }
+    }
+
+    /// Small emissive sphere standing in for a point or spot light, with the
+    /// light's intensity in every direction. Spot cones are ignored and
+    /// directional lights have no stand-in.
+    pub fn emitter(&self, radius: f32) -> Option<Sphere> {
+        if self.kind == LightKind::Directional {
+            return None;
+        }
+
+        let mut material = Principled::new(Texture::solid(0.0));
+        material.specular = 0.0;
+        material.emission = Texture::Solid(self.intensity / (PI * radius * radius) * self.color);
+
+        Some(Sphere::new(
+            self.position,
+            radius,
+            Material::Principled(Box::new(material)),
+        ))
+    }
+}
+
+/// Scene imported from a glTF 2.0 file. Every mesh primitive becomes a mesh
+/// with its own BVH that is shared by all nodes instancing it.
+pub struct GltfScene {
+    pub objects: Vec<Box<dyn Hittable>>,
+    pub cameras: Vec<CameraBuilder>,
+    pub lights: Vec<PunctualLight>,
+}
+
+type Primitives = Vec<Arc<dyn Hittable>>;
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+fn import_error(err: ::gltf::Error) -> Error {
+    match err {
+        ::gltf::Error::Io(err) => err,
+        err => Error::new(ErrorKind::InvalidData, err.to_string()),
+    }
+}
+
+/// Splits decoded image data into a color image and, if present, an alpha
+/// image.
+fn convert_image(data: &::gltf::image::Data) -> (Arc<Image>, Option<Arc<Image>>) {
+    use ::gltf::image::Format;
+
+    let (channels, size) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let read = |offset: usize| -> f32 {
+        let bytes = &data.pixels[offset..offset + size];
+        match size {
+            1 => bytes[0] as f32 / 255.0,
+            2 => u16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+            _ => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    };
+
+    let mut colors = Vec::new();
+    let mut alphas = Vec::new();
+    for pixel in 0..(data.width * data.height) as usize {
+        let offset = pixel * channels * size;
+        let channel = |c: usize| read(offset + c * size);
+        // One and two channel images are luminance with optional alpha.
+        let (color, alpha) = match channels {
+            1 => (Vec3::new(channel(0), channel(0), channel(0)), None),
+            2 => (
+                Vec3::new(channel(0), channel(0), channel(0)),
+                Some(channel(1)),
+            ),
+            3 => (Vec3::new(channel(0), channel(1), channel(2)), None),
+            _ => (
+                Vec3::new(channel(0), channel(1), channel(2)),
+                Some(channel(3)),
+            ),
+        };
+        colors.push(color);
+        if let Some(alpha) = alpha {
+            alphas.push(Vec3::new(alpha, alpha, alpha));
+        }
+    }
+
+    let color = Arc::new(Image::new(data.width, data.height, colors));
+    let alpha = if alphas.is_empty() {
+        None
+    } else {
+        Some(Arc::new(Image::new(data.width, data.height, alphas)))
+    };
+    (color, alpha)
+}
+
+struct Images {
+    color: Vec<Arc<Image>>,
+    alpha: Vec<Option<Arc<Image>>>,
+}
+
+impl Images {
+    fn texture(&self, texture: ::gltf::Texture, srgb: bool) -> Texture {
+        Texture::Image {
+            image: self.color[texture.source().index()].clone(),
+            srgb,
+        }
+    }
+
+    fn alpha(&self, texture: ::gltf::Texture) -> Option<Texture> {
+        self.alpha[texture.source().index()]
+            .as_ref()
+            .map(|image| Texture::Image {
+                image: image.clone(),
+                srgb: false,
+            })
+    }
+}
+
+fn convert_material(material: &::gltf::Material, images: &Images) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    let factor = Vec3::new(r, g, b);
+
+    let base_color = match pbr.base_color_texture() {
+        Some(info) => Texture::Scale(Box::new(images.texture(info.texture(), true)), factor),
+        None => Texture::Solid(factor),
+    };
+    let metallic_roughness = pbr
+        .metallic_roughness_texture()
+        .map(|info| images.texture(info.texture(), false));
+
+    let mut principled = Principled::from_metallic_roughness(
+        base_color,
+        metallic_roughness,
+        pbr.metallic_factor(),
+        pbr.roughness_factor(),
+    );
+
+    let [r, g, b] = material.emissive_factor();
+    let emissive = Vec3::new(r, g, b);
+    principled.emission = match material.emissive_texture() {
+        Some(info) => Texture::Scale(Box::new(images.texture(info.texture(), true)), emissive),
+        None => Texture::Solid(emissive),
+    };
+    principled.emission_strength = material.emissive_strength().unwrap_or(1.0);
+
+    let mut result = Material::Principled(Box::new(principled));
+
+    if let Some(normal) = material.normal_texture() {
+        result = Material::Bump(Box::new(Bump {
+            material: result,
+            map: BumpMap::Normal {
+                texture: images.texture(normal.texture(), false),
+                strength: normal.scale(),
+            },
+        }));
+    }
+
+    let mode = match material.alpha_mode() {
+        ::gltf::material::AlphaMode::Opaque => None,
+        ::gltf::material::AlphaMode::Mask => {
+            Some(AlphaMode::Threshold(material.alpha_cutoff().unwrap_or(0.5)))
+        }
+        ::gltf::material::AlphaMode::Blend => Some(AlphaMode::Stochastic),
+    };
+    if let Some(mode) = mode {
+        let alpha = pbr
+            .base_color_texture()
+            .and_then(|info| images.alpha(info.texture()))
+            .map(|texture| Texture::Scale(Box::new(texture), Vec3::new(a, a, a)))
+            .unwrap_or_else(|| Texture::solid(a));
+
+        result = Material::Cutout(Box::new(Cutout {
+            material: result,
+            alpha,
+            mode,
+        }));
+    }
+
+    result
+}
+
+fn default_material() -> Material {
+    Material::Principled(Box::new(Principled::from_metallic_roughness(
+        Texture::solid(1.0),
+        None,
+        1.0,
+        1.0,
+    )))
+}
+
+/// Builds a mesh from a primitive. Only triangle lists are supported, other
+/// primitive modes are skipped.
+fn load_primitive(
+    primitive: &::gltf::Primitive,
+    buffers: &[::gltf::buffer::Data],
+    material: Material,
+) -> Result<Option<Mesh>> {
+    if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+        return Ok(None);
+    }
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let positions: Vec<Vec3> = reader
+        .read_positions()
+        .ok_or_else(|| invalid_data("Mesh primitive has no positions"))?
+        .map(|[x, y, z]| Vec3::new(x, y, z))
+        .collect();
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    if indices.iter().any(|i| *i as usize >= positions.len()) {
+        return Err(invalid_data("Mesh primitive index out of range"));
+    }
+    let indices: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|face| [face[0], face[1], face[2]])
+        .collect();
+    if indices.is_empty() {
+        return Ok(None);
+    }
+
+    let mut data = MeshData::new(positions, indices, material);
+    if let Some(normals) = reader.read_normals() {
+        data = data.normals(normals.map(|[x, y, z]| Vec3::new(x, y, z)).collect());
+    }
+    if let Some(uvs) = reader.read_tex_coords(0) {
+        // glTF puts the texture origin at the top left corner of the image.
+        data = data.uvs(uvs.into_f32().map(|[u, v]| (u, 1.0 - v)).collect());
+    }
+    if let (Some(tangents), false) = (reader.read_tangents(), data.normals.is_empty()) {
+        let (tangents, bitangents) = tangents
+            .zip(data.normals.iter())
+            .map(|([x, y, z, w], n)| {
+                let tangent = Vec3::new(x, y, z);
+                (tangent, w * n.cross(tangent))
+            })
+            .unzip();
+        data = data.tangents(tangents, bitangents);
+    }
+
+    Ok(Some(Mesh::new(data)))
+}
+
+fn convert_camera(camera: &::gltf::Camera, transform: &Transform) -> CameraBuilder {
+    let builder = CameraBuilder::new()
+        .lookfrom(transform.point(Vec3::zero()))
+        .lookat(transform.point(Vec3::new(0.0, 0.0, -1.0)))
+        .vup(transform.vector(Vec3::new(0.0, 1.0, 0.0)));
+
+    match camera.projection() {
+        ::gltf::camera::Projection::Perspective(perspective) => {
+            let builder = builder.fov(perspective.yfov().to_degrees());
+            match perspective.aspect_ratio() {
+                Some(aspect_ratio) => builder.aspect_ratio(aspect_ratio),
+                None => builder,
+            }
+        }
+        ::gltf::camera::Projection::Orthographic(orthographic) => builder
+            .projection(Projection::Orthographic {
+                view_height: 2.0 * orthographic.ymag(),
+            })
+            .aspect_ratio(orthographic.xmag() / orthographic.ymag()),
+    }
+}
+
+fn convert_light(
+    light: &::gltf::khr_lights_punctual::Light,
+    transform: &Transform,
+) -> PunctualLight {
+    use ::gltf::khr_lights_punctual::Kind;
+
+    let kind = match light.kind() {
+        Kind::Directional => LightKind::Directional,
+        Kind::Point => LightKind::Point,
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => LightKind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        },
+    };
+    let [r, g, b] = light.color();
+
+    PunctualLight {
+        kind,
+        color: Vec3::new(r, g, b),
+        intensity: light.intensity(),
+        position: transform.point(Vec3::zero()),
+        direction: transform.vector(Vec3::new(0.0, 0.0, -1.0)).unit_vector(),
+        range: light.range(),
+    }
+}
+
+impl GltfScene {
+    /// Loads the default scene, or the first one, of a `.gltf` or `.glb` file
+    /// together with its external buffers and images.
+    pub fn load(path: &str) -> Result<GltfScene> {
+        let (document, buffers, images) = ::gltf::import(path).map_err(import_error)?;
+        GltfScene::from_document(&document, &buffers, &images)
+    }
+
+    /// Same as `load` for a file in memory, which can only reference embedded
+    /// buffers and images.
+    pub fn from_slice(bytes: &[u8]) -> Result<GltfScene> {
+        let (document, buffers, images) = ::gltf::import_slice(bytes).map_err(import_error)?;
+        GltfScene::from_document(&document, &buffers, &images)
+    }
+
+    fn from_document(
+        document: &::gltf::Document,
+        buffers: &[::gltf::buffer::Data],
+        images: &[::gltf::image::Data],
+    ) -> Result<GltfScene> {
+        let (color, alpha) = images.iter().map(convert_image).unzip();
+        let images = Images { color, alpha };
+        let materials: Vec<Material> = document
+            .materials()
+            .map(|material| convert_material(&material, &images))
+            .collect();
+
+        let mut meshes: Vec<Primitives> = Vec::new();
+        for mesh in document.meshes() {
+            let mut primitives: Primitives = Vec::new();
+            for primitive in mesh.primitives() {
+                let material = match primitive.material().index() {
+                    Some(index) => materials[index].clone(),
+                    None => default_material(),
+                };
+                if let Some(mesh) = load_primitive(&primitive, buffers, material)? {
+                    primitives.push(Arc::new(mesh));
+                }
+            }
+            meshes.push(primitives);
+        }
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| invalid_data("File contains no scene"))?;
+
+        let mut result = GltfScene {
+            objects: Vec::new(),
+            cameras: Vec::new(),
+            lights: Vec::new(),
+        };
+        for node in scene.nodes() {
+            result.add_node(&node, Transform::identity(), &meshes);
+        }
+
+        Ok(result)
+    }
+
+    fn add_node(&mut self, node: &::gltf::Node, parent: Transform, meshes: &[Primitives]) {
+        let transform = match Transform::from_columns(node.transform().matrix()) {
+            Some(local) => parent * local,
+            // Nodes scaled to zero are hidden together with their children.
+            None => return,
+        };
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in meshes[mesh.index()].iter() {
+                self.objects
+                    .push(Box::new(Instance::new(primitive.clone(), transform)));
+            }
+        }
+        if let Some(camera) = node.camera() {
+            self.cameras.push(convert_camera(&camera, &transform));
+        }
+        if let Some(light) = node.light() {
+            self.lights.push(convert_light(&light, &transform));
+        }
+
+        for child in node.children() {
+            self.add_node(&child, transform, meshes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+
+    /// Binary glTF with one triangle instanced by two nodes, one of them the
+    /// child of a translated parent, plus a camera and a point light.
+    fn glb() -> Vec<u8> {
+        let mut bin = Vec::new();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&v.to_le_bytes());
+        }
+
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": {"KHR_lights_punctual": {"lights": [
+                {"type": "point", "color": [1, 0.5, 0.25], "intensity": 10}
+            ]}},
+            "buffers": [{"byteLength": 36}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3,
+                "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0, 0, 1]}}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+            "cameras": [{"type": "perspective", "perspective": {"yfov": 0.5, "znear": 0.1}}],
+            "nodes": [
+                {"mesh": 0},
+                {"translation": [0, 0, -5], "children": [2, 3]},
+                {"mesh": 0, "scale": [2, 2, 2]},
+                {"translation": [0, 0, 10], "camera": 0,
+                    "extensions": {"KHR_lights_punctual": {"light": 0}}}
+            ],
+            "scenes": [{"nodes": [0, 1]}],
+            "scene": 0
+        }"#;
+        let mut json = json.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+
+        let mut glb = Vec::new();
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    #[test]
+    fn test_import_node_hierarchy() {
+        let scene = GltfScene::from_slice(&glb()).unwrap();
+        assert_eq!(scene.objects.len(), 2);
+
+        let ray = Ray::new(Vec3::new(1.5, 0.25, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = scene
+            .objects
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 15.0).abs() < 1e-4);
+
+        assert_eq!(scene.cameras.len(), 1);
+        let camera = &scene.cameras[0];
+        assert_eq!(camera.lookfrom, Vec3::new(0.0, 0.0, 5.0));
+        assert!((camera.fov - 0.5f32.to_degrees()).abs() < 1e-4);
+
+        assert_eq!(scene.lights.len(), 1);
+        let light = scene.lights[0];
+        assert_eq!(light.kind, LightKind::Point);
+        assert_eq!(light.position, Vec3::new(0.0, 0.0, 5.0));
+        assert!(light.emitter(0.1).is_some());
+    }
+
+    #[test]
+    fn test_invalid_file_is_rejected() {
+        assert!(GltfScene::from_slice(b"not a gltf file").is_err());
+    }
+}
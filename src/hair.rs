@@ -0,0 +1,209 @@
+use crate::hittable::HitRecord;
+use crate::material::{Scatter, Scatterable};
+use crate::onb::Onb;
+use crate::principled::luminance;
+use crate::ray::Ray;
+use crate::rng;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+
+/// Kajiya-Kay hair shading for curves, oriented by the tangent of the hit.
+///
+/// The diffuse lobe falls off with the sine of the angle to the fiber and the
+/// specular lobe is a cone of reflections around the fiber, as wide as
+/// `exponent` allows. Lobes are picked stochastically by their luminance.
+#[derive(Clone, Copy)]
+pub struct Hair {
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub exponent: f32,
+}
+
+/// Natural logarithm of the gamma function for `x >= 0.5`, by the Lanczos
+/// approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    let x = x - 1.0;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+    let t = x + G + 0.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Integral of `cos^n` over `[-pi/2, pi/2]`, `sqrt(pi) * gamma((n + 1) / 2)
+/// / gamma(n / 2 + 1)`.
+fn cos_power_integral(n: f32) -> f32 {
+    let n = n as f64;
+    let ratio = (ln_gamma((n + 1.0) / 2.0) - ln_gamma(n / 2.0 + 1.0)).exp();
+    (std::f64::consts::PI.sqrt() * ratio) as f32
+}
+
+impl Hair {
+    pub fn new(diffuse: Vec3) -> Hair {
+        Hair {
+            diffuse,
+            specular: Vec3::new(0.3, 0.3, 0.3),
+            exponent: 50.0,
+        }
+    }
+
+    pub fn specular(mut self, specular: Vec3, exponent: f32) -> Hair {
+        self.specular = specular;
+        self.exponent = exponent;
+        self
+    }
+}
+
+impl Scatterable for Hair {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        let frame = Onb::from_normal_tangent(hit_rec.normal, hit_rec.tangent);
+        let fiber = frame.u;
+
+        let specular_weight = luminance(self.specular);
+        let total = specular_weight + luminance(self.diffuse);
+        if total <= 0.0 {
+            return None;
+        }
+        let p_specular = specular_weight / total;
+
+        let (direction, attenuation) = if rng::random() < p_specular {
+            // Mirror the longitudinal angle across the normal plane of the
+            // fiber, spread by a Phong-like lobe, at a random azimuth.
+            let wo = -ray_in.direction.unit_vector();
+            let theta_o = fiber.dot(wo).clamp(-1.0, 1.0).asin();
+            let offset = rng::random().powf(1.0 / (self.exponent + 1.0)).acos();
+            let offset = if rng::random() < 0.5 { offset } else { -offset };
+            let theta_i = (offset - theta_o).clamp(-PI / 2.0, PI / 2.0);
+            let phi = 2.0 * PI * rng::random();
+
+            let direction =
+                theta_i.sin() * fiber + theta_i.cos() * (phi.cos() * frame.v + phi.sin() * frame.w);
+            (direction, self.specular / p_specular)
+        } else {
+            // Uniform sphere sampling; the lobe integrates to pi^2 so the
+            // weight averages to the diffuse color.
+            let direction = Vec3::random_unit_vector();
+            let sin = (1.0 - fiber.dot(direction).powi(2)).max(0.0).sqrt();
+            (
+                direction,
+                (4.0 / PI) * sin * self.diffuse / (1.0 - p_specular),
+            )
+        };
+
+        Some(Scatter {
+            attenuation,
+            ray: Ray::new(hit_rec.point, direction),
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        let fiber = Onb::from_normal_tangent(hit_rec.normal, hit_rec.tangent).u;
+
+        let sin = (1.0 - fiber.dot(direction).powi(2)).max(0.0).sqrt();
+        let diffuse = sin / (PI * PI) * self.diffuse;
+
+        let wo = -ray_in.direction.unit_vector();
+        let theta_o = fiber.dot(wo).clamp(-1.0, 1.0).asin();
+        let theta_i = fiber.dot(direction).clamp(-1.0, 1.0).asin();
+        let lobe = (theta_i + theta_o).cos().max(0.0).powf(self.exponent);
+        // The lobe is normalized over the longitudinal angle and spread
+        // evenly around the fiber.
+        let normalization = 2.0 * PI * cos_power_integral(self.exponent);
+        let specular = lobe / normalization * self.specular;
+
+        diffuse + specular
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Vec3 {
+        self.diffuse
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::testing::hit_record;
+
+    #[test]
+    fn test_specular_cone_mirrors_fiber_angle() {
+        let material =
+            Material::Hair(Hair::new(Vec3::zero()).specular(Vec3::new(1.0, 1.0, 1.0), 1e5));
+        let hit_rec = hit_record(&material);
+
+        let ray_in = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0));
+        for _ in 0..100 {
+            let scatter = material.scatter(&ray_in, &hit_rec).unwrap();
+            let direction = scatter.ray.direction.unit_vector();
+            assert!((direction.x() - 0.5f32.sqrt()).abs() < 0.02);
+            assert_eq!(scatter.attenuation, Vec3::new(1.0, 1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_diffuse_weight_averages_to_color() {
+        let hair = Hair::new(Vec3::new(0.5, 0.5, 0.5)).specular(Vec3::zero(), 1.0);
+        let material = Material::Hair(hair);
+        let hit_rec = hit_record(&material);
+
+        let ray_in = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let n = 20000;
+        let mean = (0..n)
+            .map(|_| hair.scatter(&ray_in, &hit_rec).unwrap().attenuation.x())
+            .sum::<f32>()
+            / n as f32;
+        assert!((mean - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_specular_eval_matches_scatter_weight() {
+        assert!((cos_power_integral(0.0) - PI).abs() < 1e-5);
+        assert!((cos_power_integral(1.0) - 2.0).abs() < 1e-5);
+
+        let hair = Hair::new(Vec3::zero()).specular(Vec3::new(0.5, 0.5, 0.5), 100.0);
+        let material = Material::Hair(hair);
+        let hit_rec = hit_record(&material);
+        let ray_in = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let n = 10000;
+        let mean = (0..n)
+            .map(|_| hair.scatter(&ray_in, &hit_rec).unwrap().attenuation.x())
+            .sum::<f32>()
+            / n as f32;
+
+        // Midpoint rule over the angle to the normal plane of the fiber, +x,
+        // and the azimuth around it.
+        let (steps, azimuths) = (2000, 8);
+        let mut integral = 0.0;
+        for i in 0..steps {
+            let theta = -PI / 2.0 + PI * (i as f32 + 0.5) / steps as f32;
+            for j in 0..azimuths {
+                let phi = 2.0 * PI * (j as f32 + 0.5) / azimuths as f32;
+                let direction = Vec3::new(
+                    theta.sin(),
+                    theta.cos() * phi.cos(),
+                    theta.cos() * phi.sin(),
+                );
+                let d_omega = theta.cos() * (PI / steps as f32) * (2.0 * PI / azimuths as f32);
+                integral += hair.eval(&ray_in, &hit_rec, direction).x() * d_omega;
+            }
+        }
+        assert!((integral - mean).abs() < 0.01);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::image::Image;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::io::{Error, ErrorKind, Result};
+
+/// Terrain given by a regular grid of height samples, spanning `size` from
+/// `corner`. Sample `(i, j)` lies at `x = i`, `z = j` on the grid and its
+/// height is scaled by `size.y()`.
+///
+/// Each grid cell is split into two triangles, and rays walk the cells they
+/// cross in order, so only the cells along the ray are tested.
+pub struct Heightfield {
+    corner: Vec3,
+    size: Vec3,
+    nx: usize,
+    nz: usize,
+    heights: Vec<f32>,
+    normals: Vec<Vec3>,
+    bbox: Aabb,
+    material: Material,
+}
+
+struct CellHit {
+    t: f32,
+    normal: Vec3,
+    geometric_normal: Vec3,
+    tangent: Vec3,
+    bitangent: Vec3,
+}
+
+/// Möller-Trumbore intersection returning `t` and the barycentric weights of
+/// `b` and `c`.
+fn intersect_triangle(ray: &Ray, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, f32, f32)> {
+    let e1 = b - a;
+    let e2 = c - a;
+    let pvec = ray.direction.cross(e2);
+    let det = e1.dot(pvec);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.origin - a;
+    let b1 = tvec.dot(pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = tvec.cross(e1);
+    let b2 = ray.direction.dot(qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    Some((e2.dot(qvec) * inv_det, b1, b2))
+}
+
+impl Heightfield {
+    /// Builds the terrain from `nx * nz` heights stored row by row along `x`.
+    pub fn new(
+        corner: Vec3,
+        size: Vec3,
+        nx: usize,
+        nz: usize,
+        heights: Vec<f32>,
+        material: Material,
+    ) -> Heightfield {
+        assert!(nx >= 2 && nz >= 2, "Heightfield needs at least 2x2 samples");
+        assert_eq!(heights.len(), nx * nz);
+
+        let (min, max) = heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), h| {
+                (min.min(*h), max.max(*h))
+            });
+        let bbox = Aabb::from_points(
+            Vec3::new(corner.x(), corner.y() + min * size.y(), corner.z()),
+            Vec3::new(
+                corner.x() + size.x(),
+                corner.y() + max * size.y(),
+                corner.z() + size.z(),
+            ),
+        )
+        .padded();
+
+        let mut heightfield = Heightfield {
+            corner,
+            size,
+            nx,
+            nz,
+            heights,
+            normals: Vec::new(),
+            bbox,
+            material,
+        };
+        heightfield.normals = (0..nz)
+            .flat_map(|j| (0..nx).map(move |i| (i, j)))
+            .map(|(i, j)| heightfield.vertex_normal(i, j))
+            .collect();
+        heightfield
+    }
+
+    /// Builds the terrain from the luminance of a grayscale image, with the
+    /// rows of the image running along `z`. The image must be at least 2
+    /// pixels wide and tall.
+    pub fn from_image(
+        image: &Image,
+        corner: Vec3,
+        size: Vec3,
+        material: Material,
+    ) -> Result<Heightfield> {
+        if image.width < 2 || image.height < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Heightfield image needs at least 2x2 pixels",
+            ));
+        }
+
+        let heights = image
+            .pixels
+            .iter()
+            .map(|p| 0.2126 * p.x() + 0.7152 * p.y() + 0.0722 * p.z())
+            .collect();
+        Ok(Heightfield::new(
+            corner,
+            size,
+            image.width as usize,
+            image.height as usize,
+            heights,
+            material,
+        ))
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        (
+            self.size.x() / (self.nx - 1) as f32,
+            self.size.z() / (self.nz - 1) as f32,
+        )
+    }
+
+    fn height(&self, i: usize, j: usize) -> f32 {
+        self.corner.y() + self.heights[j * self.nx + i] * self.size.y()
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Vec3 {
+        let (dx, dz) = self.cell_size();
+        Vec3::new(
+            self.corner.x() + i as f32 * dx,
+            self.height(i, j),
+            self.corner.z() + j as f32 * dz,
+        )
+    }
+
+    /// Normal from central differences of the neighboring heights.
+    fn vertex_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (dx, dz) = self.cell_size();
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.nx - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.nz - 1));
+
+        let dhdx = (self.height(i1, j) - self.height(i0, j)) / ((i1 - i0) as f32 * dx);
+        let dhdz = (self.height(i, j1) - self.height(i, j0)) / ((j1 - j0) as f32 * dz);
+        Vec3::new(-dhdx, 1.0, -dhdz).unit_vector()
+    }
+
+    /// Closest intersection with the two triangles of cell `(i, j)`.
+    fn hit_cell(&self, ray: &Ray, ray_t: Interval, i: usize, j: usize) -> Option<CellHit> {
+        let (du, dv) = (1.0 / (self.nx - 1) as f32, 1.0 / (self.nz - 1) as f32);
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let p = corners.map(|(i, j)| self.vertex(i, j));
+        let n = corners.map(|(i, j)| self.normals[j * self.nx + i]);
+
+        // Triangles (00, 10, 11) and (00, 11, 01), with the derivatives of the
+        // position along each texture axis.
+        let triangles = [
+            ([0, 1, 2], (p[1] - p[0]) / du, (p[2] - p[1]) / dv),
+            ([0, 2, 3], (p[2] - p[3]) / du, (p[3] - p[0]) / dv),
+        ];
+
+        let mut closest: Option<CellHit> = None;
+        for ([a, b, c], tangent, bitangent) in triangles {
+            let Some((t, b1, b2)) = intersect_triangle(ray, p[a], p[b], p[c]) else {
+                continue;
+            };
+            if !ray_t.surrounds(t) || closest.as_ref().is_some_and(|hit| hit.t <= t) {
+                continue;
+            }
+
+            let normal = ((1.0 - b1 - b2) * n[a] + b1 * n[b] + b2 * n[c]).unit_vector();
+            let geometric_normal = bitangent.cross(tangent).unit_vector();
+            closest = Some(CellHit {
+                t,
+                normal,
+                geometric_normal,
+                tangent,
+                bitangent,
+            });
+        }
+        closest
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let span = self.bbox.clip(ray, ray_t)?;
+        let (dx, dz) = self.cell_size();
+
+        // Walk the cells crossed by the ray over the terrain with a 2D DDA.
+        let start = ray.point_at(span.min) - self.corner;
+        let cell = |x: f32, d: f32, n: usize| ((x / d).floor().max(0.0) as usize).min(n - 2);
+        let mut i = cell(start.x(), dx, self.nx);
+        let mut j = cell(start.z(), dz, self.nz);
+
+        let axis_setup = |index: usize, d: f32, origin: f32, corner: f32, cell_size: f32| {
+            if d == 0.0 {
+                return (0, f32::INFINITY, f32::INFINITY);
+            }
+            let step = if d > 0.0 { 1 } else { -1 };
+            let boundary = corner + (index + (d > 0.0) as usize) as f32 * cell_size;
+            (step, (boundary - origin) / d, cell_size / d.abs())
+        };
+        let (step_i, mut t_max_x, t_delta_x) =
+            axis_setup(i, ray.direction.x(), ray.origin.x(), self.corner.x(), dx);
+        let (step_j, mut t_max_z, t_delta_z) =
+            axis_setup(j, ray.direction.z(), ray.origin.z(), self.corner.z(), dz);
+
+        let mut t_enter = span.min;
+        let hit = loop {
+            let t_exit = t_max_x.min(t_max_z).min(span.max);
+
+            // Skip cells that the ray passes entirely above or below.
+            let y0 = ray.point_at(t_enter).y();
+            let y1 = ray.point_at(t_exit).y();
+            let heights =
+                [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)].map(|(i, j)| self.height(i, j));
+            let min = heights.iter().fold(f32::INFINITY, |a, b| a.min(*b));
+            let max = heights.iter().fold(f32::NEG_INFINITY, |a, b| a.max(*b));
+            if y0.min(y1) <= max && y0.max(y1) >= min {
+                if let Some(hit) = self.hit_cell(ray, ray_t, i, j) {
+                    break hit;
+                }
+            }
+
+            if t_exit >= span.max {
+                return None;
+            }
+            if t_max_x < t_max_z {
+                let next = i as i64 + step_i;
+                if next < 0 || next > self.nx as i64 - 2 {
+                    return None;
+                }
+                i = next as usize;
+                t_enter = t_max_x;
+                t_max_x += t_delta_x;
+            } else {
+                let next = j as i64 + step_j;
+                if next < 0 || next > self.nz as i64 - 2 {
+                    return None;
+                }
+                j = next as usize;
+                t_enter = t_max_z;
+                t_max_z += t_delta_z;
+            }
+        };
+
+        let point = ray.point_at(hit.t);
+        let u = (point.x() - self.corner.x()) / self.size.x();
+        let v = (point.z() - self.corner.z()) / self.size.z();
+        if !self.material.alpha_test(u, v) {
+            return None;
+        }
+
+        let front_face = ray.direction.dot(hit.geometric_normal) < 0.0;
+        Some(HitRecord {
+            point,
+            normal: if front_face { hit.normal } else { -hit.normal },
+            material: &self.material,
+            t: hit.t,
+            u,
+            v,
+            tangent: hit.tangent,
+            bitangent: hit.bitangent,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    fn hills(n: usize) -> Heightfield {
+        let heights = (0..n * n)
+            .map(|k| {
+                let (x, z) = ((k % n) as f32 * 0.3, (k / n) as f32 * 0.2);
+                x.sin() * z.cos()
+            })
+            .collect();
+        Heightfield::new(
+            Vec3::new(-5.0, 0.0, -5.0),
+            Vec3::new(10.0, 2.0, 10.0),
+            n,
+            n,
+            heights,
+            material(),
+        )
+    }
+
+    #[test]
+    fn test_slope() {
+        // Heights rising by 1 per cell along x.
+        let heights = (0..9).map(|k| (k % 3) as f32).collect();
+        let heightfield = Heightfield::new(
+            Vec3::zero(),
+            Vec3::new(2.0, 1.0, 2.0),
+            3,
+            3,
+            heights,
+            material(),
+        );
+
+        let ray = Ray::new(Vec3::new(1.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+        let hit_rec = heightfield
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 3.5).abs() < 1e-5);
+        assert!(hit_rec.front_face);
+        let expected = Vec3::new(-1.0, 1.0, 0.0).unit_vector();
+        assert!((hit_rec.normal - expected).length() < 1e-5);
+        assert!((hit_rec.u - 0.75).abs() < 1e-5);
+        assert!((hit_rec.v - 0.25).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(3.0, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+        assert!(heightfield
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+
+    #[test]
+    fn test_from_image() {
+        let pixels = vec![
+            Vec3::zero(),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::zero(),
+            Vec3::zero(),
+        ];
+        let image = Image::new(2, 2, pixels);
+        let heightfield =
+            Heightfield::from_image(&image, Vec3::zero(), Vec3::new(1.0, 1.0, 1.0), material())
+                .unwrap();
+        assert!((heightfield.heights[1] - 1.0).abs() < 1e-5);
+
+        let image = Image::new(1, 3, vec![Vec3::zero(); 3]);
+        let error =
+            Heightfield::from_image(&image, Vec3::zero(), Vec3::new(1.0, 1.0, 1.0), material())
+                .err()
+                .map(|e| e.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_traversal_matches_brute_force() {
+        let heightfield = hills(64);
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+
+        for k in 0..50 {
+            let angle = k as f32 * 0.37;
+            let ray = Ray::new(
+                Vec3::new(-8.0 + 0.3 * k as f32, 4.0, -7.0),
+                Vec3::new(angle.cos(), -0.3 - 0.01 * k as f32, angle.sin().abs() + 0.1),
+            );
+
+            let brute_force = (0..63)
+                .flat_map(|j| (0..63).map(move |i| (i, j)))
+                .filter_map(|(i, j)| heightfield.hit_cell(&ray, ray_t, i, j))
+                .map(|hit| hit.t)
+                .min_by(|a, b| a.total_cmp(b));
+            let traversed = heightfield.hit(&ray, ray_t).map(|hit_rec| hit_rec.t);
+
+            match (brute_force, traversed) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-4),
+                (a, b) => assert_eq!(a, b),
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
+use crate::aabb::{self, Aabb};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
+use std::f32::consts::PI;
 
 #[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
@@ -9,15 +11,71 @@ pub struct HitRecord<'a> {
     pub normal: Vec3,
     pub material: &'a Material,
     pub t: f32,
+    pub u: f32,
+    pub v: f32,
+    /// Surface derivatives with respect to `u` and `v`, used to orient
+    /// tangent-space normal maps and bump maps.
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    /// Interpolated vertex color of meshes that have one, white otherwise.
+    pub vertex_color: Vec3,
     pub front_face: bool,
 }
 
-pub trait Hittable: Sync {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord>;
+/// Picks the closest of several candidate intersections of a primitive that
+/// lies inside `ray_t` and passes the alpha test of its material. Missing
+/// candidates can be given an infinite or NaN `t`.
+pub(crate) fn closest_hit<'a, T: Copy>(
+    candidates: &mut [(f32, T)],
+    ray_t: Interval,
+    record: impl Fn(f32, T) -> HitRecord<'a>,
+) -> Option<HitRecord<'a>> {
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    candidates
+        .iter()
+        .filter(|(t, _)| ray_t.surrounds(*t))
+        .map(|(t, part)| record(*t, *part))
+        .find(|hit_rec| hit_rec.material.alpha_test(hit_rec.u, hit_rec.v))
+}
+
+/// Texture coordinate around the `y` axis and its derivative for a point `p`
+/// relative to the axis, matching the parametrization of `Sphere`.
+pub(crate) fn azimuth(p: Vec3) -> (f32, Vec3) {
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), 2.0 * PI * Vec3::new(p.z(), 0.0, -p.x()))
+}
+
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>>;
+
+    /// Every intersection along `ray` inside `ray_t`, ordered by `t`. The
+    /// default collects them by repeatedly asking `hit` for the next one.
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord<'_>> {
+        let mut hits = Vec::new();
+        let mut min = ray_t.min;
+        while let Some(hit_rec) = self.hit(ray, Interval::new(min, ray_t.max)) {
+            min = hit_rec.t;
+            hits.push(hit_rec);
+        }
+        hits
+    }
+
+    /// Fraction of light getting through along `ray` inside `ray_t`, for
+    /// shadow rays. Surfaces block it completely; participating media let
+    /// part of it through.
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f32 {
+        if self.hit(ray, ray_t).is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb;
 }
 
 impl Hittable for Vec<Box<dyn Hittable>> {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let mut hit: Option<HitRecord> = None;
         let mut closest = ray_t.max;
 
@@ -37,4 +95,20 @@ impl Hittable for Vec<Box<dyn Hittable>> {
 
         hit
     }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f32 {
+        self.iter().fold(1.0, |transmittance, hittable| {
+            if transmittance > 0.0 {
+                transmittance * hittable.transmittance(ray, ray_t)
+            } else {
+                0.0
+            }
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.iter().fold(aabb::EMPTY, |bbox, hittable| {
+            Aabb::enclosing(bbox, hittable.bounding_box())
+        })
+    }
 }
@@ -0,0 +1,219 @@
+use crate::vec3::Vec3;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+/// RGB image with channel values normalized to `[0, 1]`.
+///
+/// Only the netpbm formats are supported (`P2`, `P3`, `P5`, `P6`), which is the
+/// same family the renderer writes its output in.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec3>,
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+struct HeaderReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeaderReader<'a> {
+    fn skip_whitespace_and_comments(&mut self) {
+        while self.pos < self.bytes.len() {
+            let c = self.bytes[self.pos];
+            if c == b'#' {
+                while self.pos < self.bytes.len() && self.bytes[self.pos] != b'\n' {
+                    self.pos += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<&'a str> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos;
+        while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(invalid_data("Unexpected end of image data"));
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .map_err(|_| invalid_data("Invalid image header"))
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        self.token()?
+            .parse()
+            .map_err(|_| invalid_data("Invalid number in image"))
+    }
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<Vec3>) -> Image {
+        assert!(width > 0 && height > 0, "Image has no pixels");
+        assert_eq!(pixels.len(), width as usize * height as usize);
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Image> {
+        let bytes = fs::read(path)?;
+        Image::parse_netpbm(&bytes)
+    }
+
+    pub fn parse_netpbm(bytes: &[u8]) -> Result<Image> {
+        let mut reader = HeaderReader { bytes, pos: 0 };
+
+        let magic = reader.token()?;
+        let (channels, binary) = match magic {
+            "P2" => (1, false),
+            "P3" => (3, false),
+            "P5" => (1, true),
+            "P6" => (3, true),
+            _ => return Err(invalid_data("Unsupported image format")),
+        };
+
+        let width = reader.number()?;
+        let height = reader.number()?;
+        let max_value = reader.number()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid_data("Invalid maximum color value"));
+        }
+
+        if width == 0 || height == 0 {
+            return Err(invalid_data("Image has no pixels"));
+        }
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(|| invalid_data("Image is too large"))?;
+        let mut values: Vec<u32> = Vec::with_capacity(count.min(bytes.len()));
+
+        if binary {
+            // Exactly one whitespace byte separates the header from the raster.
+            let mut pos = reader.pos + 1;
+            let sample_size = if max_value < 256 { 1 } else { 2 };
+            let end = count
+                .checked_mul(sample_size)
+                .and_then(|n| n.checked_add(pos))
+                .ok_or_else(|| invalid_data("Image is too large"))?;
+            if bytes.len() < end {
+                return Err(invalid_data("Unexpected end of image data"));
+            }
+            for _ in 0..count {
+                let value = if sample_size == 1 {
+                    bytes[pos] as u32
+                } else {
+                    ((bytes[pos] as u32) << 8) | bytes[pos + 1] as u32
+                };
+                values.push(value);
+                pos += sample_size;
+            }
+        } else {
+            for _ in 0..count {
+                values.push(reader.number()?);
+            }
+        }
+
+        let scale = 1.0 / max_value as f32;
+        let pixels = values
+            .chunks(channels)
+            .map(|c| {
+                if channels == 1 {
+                    let v = c[0] as f32 * scale;
+                    Vec3::new(v, v, v)
+                } else {
+                    Vec3::new(
+                        c[0] as f32 * scale,
+                        c[1] as f32 * scale,
+                        c[2] as f32 * scale,
+                    )
+                }
+            })
+            .collect();
+
+        Ok(Image::new(width, height, pixels))
+    }
+
+    pub fn pixel(&self, i: u32, j: u32) -> Vec3 {
+        let i = i.min(self.width - 1);
+        let j = j.min(self.height - 1);
+        self.pixels[(j * self.width + i) as usize]
+    }
+
+    /// Looks up the pixel nearest to normalized coordinates `(s, t)`, where
+    /// `(0, 0)` is the upper left corner of the image.
+    pub fn sample_nearest(&self, s: f32, t: f32) -> Vec3 {
+        let i = (s.clamp(0.0, 1.0) * self.width as f32) as u32;
+        let j = (t.clamp(0.0, 1.0) * self.height as f32) as u32;
+        self.pixel(i, j)
+    }
+
+    /// Bilinearly filtered lookup at normalized coordinates `(s, t)`, wrapping
+    /// around at the image borders.
+    pub fn sample_bilinear(&self, s: f32, t: f32) -> Vec3 {
+        let x = s.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = t.rem_euclid(1.0) * self.height as f32 - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+
+        let wrap = |a: f32, n: u32| (a as i64).rem_euclid(n as i64) as u32;
+        let i0 = wrap(x0, self.width);
+        let i1 = wrap(x0 + 1.0, self.width);
+        let j0 = wrap(y0, self.height);
+        let j1 = wrap(y0 + 1.0, self.height);
+
+        (1.0 - fy) * ((1.0 - fx) * self.pixel(i0, j0) + fx * self.pixel(i1, j0))
+            + fy * ((1.0 - fx) * self.pixel(i0, j1) + fx * self.pixel(i1, j1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii_ppm() {
+        let img = Image::parse_netpbm(b"P3\n# comment\n2 1\n255\n255 0 0 0 0 255\n").unwrap();
+        assert_eq!(img.width, 2);
+        assert_eq!(img.height, 1);
+        assert_eq!(img.pixel(0, 0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(img.pixel(1, 0), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_parse_binary_pgm() {
+        let mut bytes = b"P5 2 2 255\n".to_vec();
+        bytes.extend_from_slice(&[0, 255, 255, 0]);
+        let img = Image::parse_netpbm(&bytes).unwrap();
+        assert_eq!(img.pixel(0, 0), Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(img.pixel(1, 0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(img.sample_nearest(0.9, 0.9), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_truncated_image_is_rejected() {
+        assert!(Image::parse_netpbm(b"P6 4 4 255\n\x00\x00").is_err());
+    }
+
+    #[test]
+    fn test_degenerate_header_is_rejected() {
+        assert!(Image::parse_netpbm(b"P3 0 0 255\n").is_err());
+        assert!(Image::parse_netpbm(b"P6 4294967295 4294967295 255\n\x00").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::aabb::{self, Aabb};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// Places a shared object in the world with an affine transform, so the same
+/// geometry and its acceleration structure can appear several times.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    bbox: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Instance {
+        let local = object.bounding_box();
+        let mut bbox = aabb::EMPTY;
+        for corner in 0..8 {
+            let p = Vec3::new(
+                if corner & 1 == 0 {
+                    local.x.min
+                } else {
+                    local.x.max
+                },
+                if corner & 2 == 0 {
+                    local.y.min
+                } else {
+                    local.y.max
+                },
+                if corner & 4 == 0 {
+                    local.z.min
+                } else {
+                    local.z.max
+                },
+            );
+            let p = transform.point(p);
+            bbox = Aabb::enclosing(bbox, Aabb::from_points(p, p));
+        }
+
+        Instance {
+            object,
+            transform,
+            bbox,
+        }
+    }
+}
+
+impl Instance {
+    /// The ray in object space. The direction is not normalized, so `t` is
+    /// the same in both spaces.
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        let inverse = self.transform.inverse();
+        Ray::with_wavelength(
+            inverse.point(ray.origin),
+            inverse.vector(ray.direction),
+            ray.wavelength,
+        )
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let local = self.local_ray(ray);
+        let mut hit_rec = self.object.hit(&local, ray_t)?;
+        hit_rec.point = self.transform.point(hit_rec.point);
+        hit_rec.normal = self.transform.normal(hit_rec.normal).unit_vector();
+        hit_rec.tangent = self.transform.vector(hit_rec.tangent);
+        hit_rec.bitangent = self.transform.vector(hit_rec.bitangent);
+
+        Some(hit_rec)
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f32 {
+        self.object.transmittance(&self.local_ray(ray), ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use crate::testing::material;
+
+    #[test]
+    fn test_scaled_and_translated_sphere() {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Vec3::zero(), 1.0, material()));
+        let instance = Instance::new(
+            sphere,
+            Transform::translation(Vec3::new(0.0, 0.0, -5.0))
+                * Transform::scaling(Vec3::new(2.0, 1.0, 1.0)),
+        );
+
+        let ray = Ray::new(Vec3::new(-4.0, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_rec = instance
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 2.0).abs() < 1e-5);
+        assert!((hit_rec.point - Vec3::new(-2.0, 0.0, -5.0)).length() < 1e-5);
+        assert!((hit_rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-5);
+
+        let bbox = instance.bounding_box();
+        assert_eq!(bbox.x, Interval::new(-2.0, 2.0));
+        assert_eq!(bbox.z, Interval::new(-6.0, -4.0));
+    }
+}
@@ -1,13 +1,14 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Interval {
     pub min: f32,
     pub max: f32,
 }
 
-const EMPTY: Interval = Interval {
+pub const EMPTY: Interval = Interval {
     min: f32::INFINITY,
     max: f32::NEG_INFINITY,
 };
-const UNIVERSE: Interval = Interval {
+pub const UNIVERSE: Interval = Interval {
     min: f32::NEG_INFINITY,
     max: f32::INFINITY,
 };
@@ -17,6 +18,23 @@ impl Interval {
         Interval { min, max }
     }
 
+    /// Smallest interval enclosing both `a` and `b`.
+    pub fn enclosing(a: Interval, b: Interval) -> Interval {
+        Interval {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.max - self.min
+    }
+
+    pub fn expand(&self, delta: f32) -> Interval {
+        let padding = delta / 2.0;
+        Interval::new(self.min - padding, self.max + padding)
+    }
+
     pub fn contains(&self, x: f32) -> bool {
         x >= self.min && x <= self.max
     }
@@ -1,8 +1,47 @@
+pub mod aabb;
+pub mod animation;
+pub mod bump;
+pub mod bvh;
 pub mod camera;
+pub mod capsule;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod curve;
+pub mod cutout;
+pub mod cylinder;
+pub mod denoise;
+pub mod framebuffer;
+pub mod gltf;
+pub mod hair;
+pub mod heightfield;
 pub mod hittable;
+pub mod image;
+pub mod instance;
 pub mod interval;
+pub mod light;
 pub mod material;
+pub mod mesh;
+pub mod microfacet;
+pub mod mix;
+pub mod mtl;
+pub mod onb;
+pub mod ply;
+pub mod pointcloud;
+pub mod principled;
+pub mod quad;
 pub mod ray;
 pub mod rng;
+pub mod sdf;
+pub mod sky;
+pub mod spectrum;
 pub mod sphere;
+pub mod stl;
+pub mod subsurface;
+#[cfg(test)]
+mod testing;
+pub mod texture;
+pub mod torus;
+pub mod transform;
 pub mod vec3;
+pub mod volume;
@@ -0,0 +1,296 @@
+use crate::onb::Onb;
+use crate::rng;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+
+/// How the light of a point or spot light weakens with distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    InverseSquare,
+}
+
+impl Falloff {
+    fn attenuation(&self, distance: f32) -> f32 {
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 / distance,
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+        }
+    }
+}
+
+/// Light emitted from a single point equally in all directions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub falloff: Falloff,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, color: Vec3, intensity: f32) -> PointLight {
+        PointLight {
+            position,
+            color,
+            intensity,
+            falloff: Falloff::InverseSquare,
+        }
+    }
+
+    pub fn falloff(mut self, falloff: Falloff) -> PointLight {
+        self.falloff = falloff;
+        self
+    }
+}
+
+/// Point light restricted to a cone around `direction`. The light fades out
+/// smoothly over the outer `soft_edge` degrees of the cone, whose half-angle
+/// is `cone_angle` degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub falloff: Falloff,
+    pub cone_angle: f32,
+    pub soft_edge: f32,
+}
+
+impl SpotLight {
+    pub fn new(position: Vec3, direction: Vec3, color: Vec3, intensity: f32) -> SpotLight {
+        SpotLight {
+            position,
+            direction,
+            color,
+            intensity,
+            falloff: Falloff::InverseSquare,
+            cone_angle: 30.0,
+            soft_edge: 5.0,
+        }
+    }
+
+    pub fn cone(mut self, cone_angle: f32, soft_edge: f32) -> SpotLight {
+        self.cone_angle = cone_angle;
+        self.soft_edge = soft_edge;
+        self
+    }
+
+    pub fn falloff(mut self, falloff: Falloff) -> SpotLight {
+        self.falloff = falloff;
+        self
+    }
+
+    /// Fraction of the light reaching points in unit `direction` from the
+    /// light.
+    fn cone_weight(&self, direction: Vec3) -> f32 {
+        let cos_outer = self.cone_angle.to_radians().cos();
+        let cos_inner = (self.cone_angle - self.soft_edge)
+            .max(0.0)
+            .to_radians()
+            .cos();
+        let cos = direction.dot(self.direction.unit_vector());
+
+        if cos_inner - cos_outer <= 0.0 {
+            return if cos >= cos_outer { 1.0 } else { 0.0 };
+        }
+        let t = ((cos - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// Light from a distant source such as the sun, travelling along
+/// `direction`. A non-zero `angular_diameter` in degrees gives soft shadows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub angular_diameter: f32,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, color: Vec3, intensity: f32) -> DirectionalLight {
+        DirectionalLight {
+            direction,
+            color,
+            intensity,
+            angular_diameter: 0.0,
+        }
+    }
+
+    pub fn angular_diameter(mut self, angular_diameter: f32) -> DirectionalLight {
+        self.angular_diameter = angular_diameter;
+        self
+    }
+}
+
+/// Incident light at a point from a single light.
+pub struct LightSample {
+    /// Unit direction from the point towards the light.
+    pub direction: Vec3,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: f32,
+    /// Light arriving at the point, before shadowing.
+    pub radiance: Vec3,
+}
+
+/// Light that has no geometry, so it can't be hit by scattered rays and is
+/// only seen through shadow rays cast towards it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    /// Samples the light arriving at `point`, or `None` if the light doesn't
+    /// reach it.
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        match self {
+            Light::Point(light) => {
+                let offset = light.position - point;
+                let distance = offset.length();
+                if distance <= 0.0 {
+                    return None;
+                }
+
+                Some(LightSample {
+                    direction: offset / distance,
+                    distance,
+                    radiance: light.intensity * light.falloff.attenuation(distance) * light.color,
+                })
+            }
+            Light::Spot(light) => {
+                let offset = light.position - point;
+                let distance = offset.length();
+                if distance <= 0.0 {
+                    return None;
+                }
+                let direction = offset / distance;
+                let weight = light.cone_weight(-direction);
+                if weight <= 0.0 {
+                    return None;
+                }
+
+                Some(LightSample {
+                    direction,
+                    distance,
+                    radiance: weight
+                        * light.intensity
+                        * light.falloff.attenuation(distance)
+                        * light.color,
+                })
+            }
+            Light::Directional(light) => {
+                let to_light = -light.direction.unit_vector();
+                let direction = if light.angular_diameter > 0.0 {
+                    // Uniform direction within the cone subtended by the
+                    // source.
+                    let cos_max = (light.angular_diameter / 2.0).to_radians().cos();
+                    let cos_theta = 1.0 - rng::random() * (1.0 - cos_max);
+                    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                    let phi = 2.0 * PI * rng::random();
+
+                    Onb::from_w(to_light).local(Vec3::new(
+                        sin_theta * phi.cos(),
+                        sin_theta * phi.sin(),
+                        cos_theta,
+                    ))
+                } else {
+                    to_light
+                };
+
+                Some(LightSample {
+                    direction,
+                    distance: f32::INFINITY,
+                    radiance: light.intensity * light.color,
+                })
+            }
+        }
+    }
+}
+
+/// Named set of lights. Besides adding to the image, the light of each group
+/// is rendered to its own layer so it can be rebalanced in compositing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightGroup {
+    pub name: String,
+    pub lights: Vec<Light>,
+}
+
+impl LightGroup {
+    pub fn new(name: &str) -> LightGroup {
+        LightGroup {
+            name: name.to_string(),
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn light(mut self, light: Light) -> LightGroup {
+        self.lights.push(light);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_light_falloff() {
+        let light = PointLight::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 8.0);
+
+        let sample = Light::Point(light).sample(Vec3::zero()).unwrap();
+        assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.radiance, Vec3::new(2.0, 2.0, 2.0));
+
+        let sample = Light::Point(light.falloff(Falloff::Linear))
+            .sample(Vec3::zero())
+            .unwrap();
+        assert_eq!(sample.radiance, Vec3::new(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn test_spot_light_soft_edge() {
+        let light = Light::Spot(
+            SpotLight::new(
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, -1.0, 0.0),
+                Vec3::new(1.0, 1.0, 1.0),
+                1.0,
+            )
+            .cone(45.0, 10.0),
+        );
+        let at_angle = |degrees: f32| Vec3::new(degrees.to_radians().tan(), 0.0, 0.0);
+
+        let center = light.sample(Vec3::zero()).unwrap().radiance.x();
+        assert_eq!(center, 1.0);
+        let inner = light.sample(at_angle(34.0)).unwrap();
+        assert!((inner.radiance.x() * inner.distance * inner.distance - 1.0).abs() < 1e-4);
+        let edge = light.sample(at_angle(40.0)).unwrap();
+        let edge = edge.radiance.x() * edge.distance * edge.distance;
+        assert!(edge > 0.0 && edge < 1.0);
+        assert!(light.sample(at_angle(46.0)).is_none());
+    }
+
+    #[test]
+    fn test_directional_light_cone() {
+        let light = Light::Directional(
+            DirectionalLight::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 3.0)
+                .angular_diameter(10.0),
+        );
+
+        for _ in 0..100 {
+            let sample = light.sample(Vec3::zero()).unwrap();
+            assert!(sample.direction.y() >= 5.0f32.to_radians().cos() - 1e-5);
+            assert_eq!(sample.distance, f32::INFINITY);
+            assert_eq!(sample.radiance, Vec3::new(3.0, 3.0, 3.0));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use rt_rs::bvh::BvhNode;
 use rt_rs::camera::CameraBuilder;
 use rt_rs::hittable::Hittable;
 use rt_rs::material::{Dielectric, Lambertian, Material, Metal};
@@ -18,13 +19,14 @@ fn main() {
         .img_width(1200)
         .samples_per_pixel(100)
         .max_depth(50)
-        .fov(20)
+        .fov(20.0)
         .lookfrom(Vec3::new(13.0, 2.0, 3.0))
         .lookat(Vec3::new(0.0, 0.0, 0.0))
         .vup(Vec3::new(0.0, 1.0, 0.0))
         .defocus_angle(0.6)
         .focus_dist(10.0)
-        .build();
+        .build()
+        .expect("Invalid camera");
 
     let ground_material = Material::Lambertian(Lambertian {
         albedo: Vec3::new(0.8, 0.8, 0.0),
@@ -54,14 +56,14 @@ fn main() {
                     let fuzz = rng::random_in_range(0.0, 0.5);
                     Material::Metal(Metal { albedo, fuzz })
                 } else {
-                    Material::Dielectric(Dielectric { ir: 1.5 })
+                    Material::Dielectric(Dielectric::new(1.5))
                 };
                 world.push(Box::new(Sphere::new(center, 0.2, material)));
             }
         }
     }
 
-    let material1 = Material::Dielectric(Dielectric { ir: 1.5 });
+    let material1 = Material::Dielectric(Dielectric::new(1.5));
     world.push(Box::new(Sphere::new(
         Vec3::new(0.0, 1.0, 0.0),
         1.0,
@@ -87,5 +89,8 @@ fn main() {
         material3,
     )));
 
-    camera.render(&world);
+    let world = BvhNode::new(world);
+
+    let img = camera.render(&world);
+    img.write_ppm("img.ppm").expect("Unable to write image");
 }
@@ -1,7 +1,17 @@
+use crate::bump::Bump;
+use crate::cutout::Cutout;
+use crate::hair::Hair;
 use crate::hittable::HitRecord;
+use crate::microfacet::{self, ComplexIor, Ggx};
+use crate::mix::{Layered, Mix};
+use crate::onb::Onb;
+use crate::principled::Principled;
 use crate::ray::Ray;
 use crate::rng;
+use crate::spectrum;
+use crate::subsurface::Subsurface;
 use crate::vec3::Vec3;
+use std::f32::consts::PI;
 
 pub struct Scatter {
     pub attenuation: Vec3,
@@ -10,13 +20,62 @@ pub struct Scatter {
 
 pub trait Scatterable {
     fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter>;
+
+    fn emitted(&self, _hit_rec: &HitRecord) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// BSDF times the cosine term for light arriving from unit `direction`,
+    /// used to shade with lights that scattered rays can't hit. Mirrors and
+    /// smooth glass don't respond to such lights, and rough glass only through
+    /// its reflection lobe.
+    fn eval(&self, _ray_in: &Ray, _hit_rec: &HitRecord, _direction: Vec3) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Surface color at the hit, independent of the sampled direction, which
+    /// guides the denoiser. Clear materials are white.
+    fn albedo(&self, _hit_rec: &HitRecord) -> Vec3 {
+        Vec3::new(1.0, 1.0, 1.0)
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub enum Material {
     Lambertian(Lambertian),
     Metal(Metal),
     Dielectric(Dielectric),
+    Conductor(Conductor),
+    RoughDielectric(RoughDielectric),
+    Principled(Box<Principled>),
+    Bump(Box<Bump>),
+    Cutout(Box<Cutout>),
+    Mix(Box<Mix>),
+    Layered(Box<Layered>),
+    Hair(Hair),
+    Isotropic(Isotropic),
+    Subsurface(Subsurface),
+}
+
+impl Material {
+    /// Fraction of rays that find the surface present at texture coordinates
+    /// `(u, v)`.
+    pub fn opacity(&self, u: f32, v: f32) -> f32 {
+        match self {
+            Material::Cutout(c) => c.opacity(u, v) * c.material.opacity(u, v),
+            Material::Bump(b) => b.material.opacity(u, v),
+            Material::Mix(m) => m.opacity(u, v),
+            Material::Layered(l) => l.base.opacity(u, v),
+            _ => 1.0,
+        }
+    }
+
+    /// Whether the surface is present at texture coordinates `(u, v)`.
+    /// Hittables skip intersections for which this returns `false`.
+    pub fn alpha_test(&self, u: f32, v: f32) -> bool {
+        let alpha = self.opacity(u, v);
+        alpha >= 1.0 || (alpha > 0.0 && rng::random() < alpha)
+    }
 }
 
 impl Scatterable for Material {
@@ -25,6 +84,64 @@ impl Scatterable for Material {
             Material::Lambertian(l) => l.scatter(ray_in, hit_rec),
             Material::Metal(m) => m.scatter(ray_in, hit_rec),
             Material::Dielectric(d) => d.scatter(ray_in, hit_rec),
+            Material::Conductor(c) => c.scatter(ray_in, hit_rec),
+            Material::RoughDielectric(d) => d.scatter(ray_in, hit_rec),
+            Material::Principled(p) => p.scatter(ray_in, hit_rec),
+            Material::Bump(b) => b.scatter(ray_in, hit_rec),
+            Material::Cutout(c) => c.scatter(ray_in, hit_rec),
+            Material::Mix(m) => m.scatter(ray_in, hit_rec),
+            Material::Layered(l) => l.scatter(ray_in, hit_rec),
+            Material::Hair(h) => h.scatter(ray_in, hit_rec),
+            Material::Isotropic(i) => i.scatter(ray_in, hit_rec),
+            Material::Subsurface(s) => s.scatter(ray_in, hit_rec),
+        }
+    }
+
+    fn emitted(&self, hit_rec: &HitRecord) -> Vec3 {
+        match self {
+            Material::Principled(p) => p.emitted(hit_rec),
+            Material::Bump(b) => b.emitted(hit_rec),
+            Material::Cutout(c) => c.emitted(hit_rec),
+            Material::Mix(m) => m.emitted(hit_rec),
+            Material::Layered(l) => l.emitted(hit_rec),
+            Material::Isotropic(i) => i.emitted(hit_rec),
+            _ => Vec3::zero(),
+        }
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        match self {
+            Material::Lambertian(l) => l.eval(ray_in, hit_rec, direction),
+            Material::Metal(m) => m.eval(ray_in, hit_rec, direction),
+            Material::Dielectric(d) => d.eval(ray_in, hit_rec, direction),
+            Material::Conductor(c) => c.eval(ray_in, hit_rec, direction),
+            Material::RoughDielectric(d) => d.eval(ray_in, hit_rec, direction),
+            Material::Principled(p) => p.eval(ray_in, hit_rec, direction),
+            Material::Bump(b) => b.eval(ray_in, hit_rec, direction),
+            Material::Cutout(c) => c.eval(ray_in, hit_rec, direction),
+            Material::Mix(m) => m.eval(ray_in, hit_rec, direction),
+            Material::Layered(l) => l.eval(ray_in, hit_rec, direction),
+            Material::Hair(h) => h.eval(ray_in, hit_rec, direction),
+            Material::Isotropic(i) => i.eval(ray_in, hit_rec, direction),
+            Material::Subsurface(s) => s.eval(ray_in, hit_rec, direction),
+        }
+    }
+
+    fn albedo(&self, hit_rec: &HitRecord) -> Vec3 {
+        match self {
+            Material::Lambertian(l) => l.albedo(hit_rec),
+            Material::Metal(m) => m.albedo(hit_rec),
+            Material::Dielectric(d) => d.albedo(hit_rec),
+            Material::Conductor(c) => c.albedo(hit_rec),
+            Material::RoughDielectric(d) => d.albedo(hit_rec),
+            Material::Principled(p) => p.albedo(hit_rec),
+            Material::Bump(b) => b.albedo(hit_rec),
+            Material::Cutout(c) => c.albedo(hit_rec),
+            Material::Mix(m) => m.albedo(hit_rec),
+            Material::Layered(l) => l.albedo(hit_rec),
+            Material::Hair(h) => h.albedo(hit_rec),
+            Material::Isotropic(i) => i.albedo(hit_rec),
+            Material::Subsurface(s) => s.albedo(hit_rec),
         }
     }
 }
@@ -47,6 +164,43 @@ impl Scatterable for Lambertian {
             ray: scattered,
         })
     }
+
+    fn eval(&self, _: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        self.albedo * (hit_rec.normal.dot(direction).max(0.0) / PI)
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Vec3 {
+        self.albedo
+    }
+}
+
+/// Phase function of a participating medium, scattering uniformly in all
+/// directions. `emission` is the radiance added at each collision.
+#[derive(Clone, Copy)]
+pub struct Isotropic {
+    pub albedo: Vec3,
+    pub emission: Vec3,
+}
+
+impl Scatterable for Isotropic {
+    fn scatter(&self, _: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        Some(Scatter {
+            attenuation: self.albedo,
+            ray: Ray::new(hit_rec.point, Vec3::random_unit_vector()),
+        })
+    }
+
+    fn emitted(&self, _: &HitRecord) -> Vec3 {
+        self.emission
+    }
+
+    fn eval(&self, _: &Ray, _: &HitRecord, _: Vec3) -> Vec3 {
+        self.albedo / (4.0 * PI)
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Vec3 {
+        self.albedo
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -68,14 +222,97 @@ impl Scatterable for Metal {
             ray: reflected,
         })
     }
+
+    /// Fuzzy reflection approximated by a GGX lobe whose alpha, half of
+    /// `fuzz`, matches the spread of the perturbed half vectors.
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        if self.fuzz <= 0.0 {
+            return Vec3::zero();
+        }
+        let ggx = Ggx::from_roughness((self.fuzz / 2.0).sqrt(), 0.0);
+        let onb = Onb::from_w(hit_rec.normal);
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        let wi = onb.to_local(direction);
+
+        self.albedo * ggx.reflection(wo, wi)
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Vec3 {
+        self.albedo
+    }
+}
+
+/// Wavelength dependent index of refraction, with wavelengths in nanometers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dispersion {
+    /// `n = a + b / lambda^2`, with `lambda` in micrometers.
+    Cauchy { a: f32, b: f32 },
+    /// `n^2 = 1 + sum(b_i lambda^2 / (lambda^2 - c_i))`, with `lambda` in
+    /// micrometers.
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
 }
 
+impl Dispersion {
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.039_612, 0.231_792_34, 1.010_469_4],
+        c: [0.006_000_699, 0.020_017_914, 103.560_65],
+    };
+    pub const FUSED_SILICA: Dispersion = Dispersion::Sellmeier {
+        b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        c: [0.004_679_148, 0.013_512_063, 97.934_004],
+    };
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier {
+        b: [0.330_6, 4.335_6, 0.0],
+        c: [0.030_625, 0.011_236, 0.0],
+    };
+
+    pub fn ior(&self, wavelength: f32) -> f32 {
+        let lambda = wavelength / 1000.0;
+        let lambda2 = lambda * lambda;
+
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / lambda2,
+            Dispersion::Sellmeier { b, c } => (1.0
+                + b.iter()
+                    .zip(c.iter())
+                    .map(|(b, c)| b * lambda2 / (lambda2 - c))
+                    .sum::<f32>())
+            .sqrt(),
+        }
+    }
+}
+
+pub(crate) fn beer_lambert(absorption: Vec3, distance: f32) -> Vec3 {
+    Vec3::new(
+        (-absorption.x() * distance).exp(),
+        (-absorption.y() * distance).exp(),
+        (-absorption.z() * distance).exp(),
+    )
+}
+
+/// Smooth glass. `absorption` is the Beer-Lambert coefficient per unit of
+/// distance travelled inside the medium; thin-walled surfaces (windows, soap
+/// bubbles) don't bend light and apply it once per unit of wall thickness.
+/// When `dispersion` is set the path is split into a single wavelength on the
+/// first hit and `ir` is ignored.
 #[derive(Clone, Copy)]
 pub struct Dielectric {
     pub ir: f32,
+    pub absorption: Vec3,
+    pub thin_walled: bool,
+    pub dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
+    pub fn new(ir: f32) -> Dielectric {
+        Dielectric {
+            ir,
+            absorption: Vec3::zero(),
+            thin_walled: false,
+            dispersion: None,
+        }
+    }
+
     pub fn reflectance(cos: f32, ref_idx: f32) -> f32 {
         let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         let r0 = r0 * r0;
@@ -85,13 +322,45 @@ impl Dielectric {
 
 impl Scatterable for Dielectric {
     fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
-        let refraction_ratio = if hit_rec.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
+        let mut attenuation = Vec3::new(1.0, 1.0, 1.0);
+        let mut wavelength = ray_in.wavelength;
+
+        let ir = match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(lambda)) => dispersion.ior(lambda),
+            (Some(dispersion), None) => {
+                let lambda = spectrum::sample_wavelength();
+                attenuation = spectrum::wavelength_to_rgb(lambda);
+                wavelength = Some(lambda);
+                dispersion.ior(lambda)
+            }
+            (None, _) => self.ir,
         };
+
         let unit_direction = ray_in.direction.unit_vector();
         let cos_theta = f32::min((-unit_direction).dot(hit_rec.normal), 1.0);
+
+        if self.thin_walled {
+            // Reflection summed over the inter-reflections inside the wall.
+            let r = Dielectric::reflectance(cos_theta, 1.0 / ir);
+            let direction = if 2.0 * r / (1.0 + r) > rng::random() {
+                Vec3::reflect(unit_direction, hit_rec.normal)
+            } else {
+                attenuation = attenuation * beer_lambert(self.absorption, 1.0 / cos_theta);
+                unit_direction
+            };
+
+            return Some(Scatter {
+                attenuation,
+                ray: Ray::with_wavelength(hit_rec.point, direction, wavelength),
+            });
+        }
+
+        let refraction_ratio = if hit_rec.front_face { 1.0 / ir } else { ir };
+        if !hit_rec.front_face {
+            let distance = hit_rec.t * ray_in.direction.length();
+            attenuation = attenuation * beer_lambert(self.absorption, distance);
+        }
+
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = (refraction_ratio * sin_theta) > 1.0;
         let direction = if cannot_refract
@@ -101,11 +370,184 @@ impl Scatterable for Dielectric {
         } else {
             Vec3::refract(unit_direction, hit_rec.normal, refraction_ratio)
         };
-        let refracted = Ray::new(hit_rec.point, direction);
+        let refracted = Ray::with_wavelength(hit_rec.point, direction, wavelength);
 
         Some(Scatter {
-            attenuation: Vec3::new(1.0, 1.0, 1.0),
+            attenuation,
             ray: refracted,
         })
     }
 }
+
+/// Rough metal using the GGX microfacet model with a complex index of
+/// refraction. Light that would scatter below the surface is absorbed, so the
+/// material never reflects more energy than it receives.
+#[derive(Clone, Copy)]
+pub struct Conductor {
+    pub eta: Vec3,
+    pub k: Vec3,
+    pub roughness: f32,
+    pub anisotropy: f32,
+}
+
+impl Conductor {
+    pub fn new(ior: ComplexIor, roughness: f32, anisotropy: f32) -> Conductor {
+        Conductor {
+            eta: ior.eta,
+            k: ior.k,
+            roughness,
+            anisotropy,
+        }
+    }
+}
+
+impl Scatterable for Conductor {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        let ggx = Ggx::from_roughness(self.roughness, self.anisotropy);
+        let onb = Onb::from_w(hit_rec.normal);
+
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let wm = ggx.sample_visible_normal(wo);
+        let wi = Vec3::reflect(-wo, wm);
+        if wi.z() <= 0.0 {
+            return None;
+        }
+
+        let fresnel = microfacet::fresnel_conductor(wo.dot(wm), self.eta, self.k);
+        let attenuation = fresnel * (ggx.g2(wo, wi) / ggx.g1(wo));
+
+        Some(Scatter {
+            attenuation,
+            ray: Ray::new(hit_rec.point, onb.local(wi)),
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        let ggx = Ggx::from_roughness(self.roughness, self.anisotropy);
+        let onb = Onb::from_w(hit_rec.normal);
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        let wi = onb.to_local(direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Vec3::zero();
+        }
+        let wm = (wo + wi).unit_vector();
+
+        microfacet::fresnel_conductor(wo.dot(wm), self.eta, self.k) * ggx.reflection(wo, wi)
+    }
+
+    /// Reflectance at normal incidence.
+    fn albedo(&self, _: &HitRecord) -> Vec3 {
+        microfacet::fresnel_conductor(1.0, self.eta, self.k)
+    }
+}
+
+/// Rough glass using the GGX microfacet model for both reflection and
+/// transmission.
+#[derive(Clone, Copy)]
+pub struct RoughDielectric {
+    pub ir: f32,
+    pub roughness: f32,
+    pub anisotropy: f32,
+}
+
+impl Scatterable for RoughDielectric {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        let ggx = Ggx::from_roughness(self.roughness, self.anisotropy);
+        let onb = Onb::from_w(hit_rec.normal);
+        let refraction_ratio = if hit_rec.front_face {
+            1.0 / self.ir
+        } else {
+            self.ir
+        };
+
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let wm = ggx.sample_visible_normal(wo);
+        let fresnel = microfacet::fresnel_dielectric(wo.dot(wm), refraction_ratio);
+
+        let wi = if rng::random() < fresnel {
+            let wi = Vec3::reflect(-wo, wm);
+            if wi.z() <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            let wi = Vec3::refract(-wo, wm, refraction_ratio);
+            if wi.z() >= 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        let weight = ggx.g2(wo, wi) / ggx.g1(wo);
+
+        Some(Scatter {
+            attenuation: Vec3::new(weight, weight, weight),
+            ray: Ray::new(hit_rec.point, onb.local(wi)),
+        })
+    }
+
+    /// Only the reflection lobe; light arriving through the surface is left to
+    /// the transmitted paths.
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        let ggx = Ggx::from_roughness(self.roughness, self.anisotropy);
+        let onb = Onb::from_w(hit_rec.normal);
+        let refraction_ratio = if hit_rec.front_face {
+            1.0 / self.ir
+        } else {
+            self.ir
+        };
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        let wi = onb.to_local(direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Vec3::zero();
+        }
+        let wm = (wo + wi).unit_vector();
+        let reflection =
+            microfacet::fresnel_dielectric(wo.dot(wm), refraction_ratio) * ggx.reflection(wo, wi);
+
+        Vec3::new(reflection, reflection, reflection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::hit_record;
+
+    #[test]
+    fn test_glossy_eval() {
+        let ray = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0));
+        let mirror = Vec3::new(1.0, 0.0, 1.0).unit_vector();
+        let below = Vec3::new(1.0, 0.0, -1.0).unit_vector();
+
+        let glass = Material::RoughDielectric(RoughDielectric {
+            ir: 1.5,
+            roughness: 0.3,
+            anisotropy: 0.0,
+        });
+        let hit_rec = hit_record(&glass);
+        assert!(glass.eval(&ray, &hit_rec, mirror).x() > 0.0);
+        assert_eq!(glass.eval(&ray, &hit_rec, below), Vec3::zero());
+
+        let metal = |fuzz: f32| {
+            Material::Metal(Metal {
+                albedo: Vec3::new(0.9, 0.5, 0.1),
+                fuzz,
+            })
+        };
+        let mirror_metal = metal(0.0);
+        assert_eq!(
+            mirror_metal.eval(&ray, &hit_record(&mirror_metal), mirror),
+            Vec3::zero()
+        );
+        let fuzzy = metal(0.3);
+        let color = fuzzy.eval(&ray, &hit_record(&fuzzy), mirror);
+        assert!(color.x() > color.y() && color.y() > color.z() && color.z() > 0.0);
+    }
+}
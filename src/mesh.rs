@@ -0,0 +1,341 @@
+use crate::aabb::{self, Aabb};
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// Indexed triangle mesh. Per-vertex attributes are optional and either empty
+/// or the same length as `positions`.
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub tangents: Vec<Vec3>,
+    pub bitangents: Vec<Vec3>,
+    /// Linear RGB vertex colors, read by `Texture::VertexColor`.
+    pub colors: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+    pub material: Material,
+}
+
+impl MeshData {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[u32; 3]>, material: Material) -> MeshData {
+        MeshData {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            tangents: Vec::new(),
+            bitangents: Vec::new(),
+            colors: Vec::new(),
+            indices,
+            material,
+        }
+    }
+
+    pub fn normals(mut self, normals: Vec<Vec3>) -> MeshData {
+        self.normals = normals;
+        self
+    }
+
+    pub fn uvs(mut self, uvs: Vec<(f32, f32)>) -> MeshData {
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn tangents(mut self, tangents: Vec<Vec3>, bitangents: Vec<Vec3>) -> MeshData {
+        self.tangents = tangents;
+        self.bitangents = bitangents;
+        self
+    }
+
+    pub fn colors(mut self, colors: Vec<Vec3>) -> MeshData {
+        self.colors = colors;
+        self
+    }
+
+    /// Derivatives of the position with respect to the texture coordinates
+    /// across face `face`, or `None` if the mesh has no usable UVs there.
+    fn face_derivatives(&self, face: [u32; 3]) -> Option<(Vec3, Vec3)> {
+        if self.uvs.is_empty() {
+            return None;
+        }
+        let [a, b, c] = face.map(|i| i as usize);
+
+        let e1 = self.positions[b] - self.positions[a];
+        let e2 = self.positions[c] - self.positions[a];
+        let du1 = self.uvs[b].0 - self.uvs[a].0;
+        let dv1 = self.uvs[b].1 - self.uvs[a].1;
+        let du2 = self.uvs[c].0 - self.uvs[a].0;
+        let dv2 = self.uvs[c].1 - self.uvs[a].1;
+
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        Some((
+            (dv2 * e1 - dv1 * e2) * inv_det,
+            (du1 * e2 - du2 * e1) * inv_det,
+        ))
+    }
+
+    /// Computes smooth per-vertex tangents from the texture coordinates by
+    /// accumulating the UV derivatives of the adjacent faces.
+    pub fn compute_tangents(&mut self) {
+        if self.uvs.is_empty() {
+            return;
+        }
+
+        let mut tangents = vec![Vec3::zero(); self.positions.len()];
+        let mut bitangents = vec![Vec3::zero(); self.positions.len()];
+
+        for face in self.indices.iter() {
+            if let Some((dpdu, dpdv)) = self.face_derivatives(*face) {
+                for i in face {
+                    tangents[*i as usize] = tangents[*i as usize] + dpdu;
+                    bitangents[*i as usize] = bitangents[*i as usize] + dpdv;
+                }
+            }
+        }
+
+        self.tangents = tangents;
+        self.bitangents = bitangents;
+    }
+}
+
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    face: [u32; 3],
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<MeshData>, index: usize) -> Triangle {
+        let face = mesh.indices[index];
+        Triangle { mesh, face }
+    }
+
+    fn vertex(&self, n: usize) -> Vec3 {
+        self.mesh.positions[self.face[n] as usize]
+    }
+
+    fn interpolate(&self, values: &[Vec3], b0: f32, b1: f32, b2: f32) -> Vec3 {
+        let [a, b, c] = self.face.map(|i| values[i as usize]);
+        b0 * a + b1 * b + b2 * c
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        // Möller-Trumbore intersection.
+        let p0 = self.vertex(0);
+        let e1 = self.vertex(1) - p0;
+        let e2 = self.vertex(2) - p0;
+
+        let pvec = ray.direction.cross(e2);
+        let det = e1.dot(pvec);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = ray.origin - p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = tvec.cross(e1);
+        let b2 = ray.direction.dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        let geometric_normal = e1.cross(e2).unit_vector();
+        let front_face = ray.direction.dot(geometric_normal) < 0.0;
+
+        let mesh = &self.mesh;
+        let mut normal = if mesh.normals.is_empty() {
+            geometric_normal
+        } else {
+            self.interpolate(&mesh.normals, b0, b1, b2).unit_vector()
+        };
+        if !front_face {
+            normal = -normal;
+        }
+
+        let (u, v) = if mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let [a, b, c] = self.face.map(|i| mesh.uvs[i as usize]);
+            (
+                b0 * a.0 + b1 * b.0 + b2 * c.0,
+                b0 * a.1 + b1 * b.1 + b2 * c.1,
+            )
+        };
+        if !mesh.material.alpha_test(u, v) {
+            return None;
+        }
+
+        let (tangent, bitangent) = if !mesh.tangents.is_empty() {
+            (
+                self.interpolate(&mesh.tangents, b0, b1, b2),
+                self.interpolate(&mesh.bitangents, b0, b1, b2),
+            )
+        } else {
+            mesh.face_derivatives(self.face).unwrap_or((e1, e2))
+        };
+        let vertex_color = if mesh.colors.is_empty() {
+            Vec3::new(1.0, 1.0, 1.0)
+        } else {
+            self.interpolate(&mesh.colors, b0, b1, b2)
+        };
+
+        Some(HitRecord {
+            point: ray.point_at(t),
+            normal,
+            material: &mesh.material,
+            t,
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_color,
+            front_face,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::enclosing(
+            Aabb::from_points(self.vertex(0), self.vertex(1)),
+            Aabb::from_points(self.vertex(2), self.vertex(2)),
+        )
+        .padded()
+    }
+}
+
+/// Triangle mesh with its own BVH, so it can be added to the world as a
+/// single object. A mesh without faces is never hit.
+pub struct Mesh {
+    bvh: Option<BvhNode>,
+}
+
+impl Mesh {
+    pub fn new(mut data: MeshData) -> Mesh {
+        if data.tangents.is_empty() {
+            data.compute_tangents();
+        }
+
+        let data = Arc::new(data);
+        let triangles: Vec<Box<dyn Hittable>> = (0..data.indices.len())
+            .map(|i| {
+                let triangle: Box<dyn Hittable> = Box::new(Triangle::new(data.clone(), i));
+                triangle
+            })
+            .collect();
+
+        Mesh {
+            bvh: (!triangles.is_empty()).then(|| BvhNode::new(triangles)),
+        }
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.bvh.as_ref()?.hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh
+            .as_ref()
+            .map_or(aabb::EMPTY, |bvh| bvh.bounding_box())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+    use crate::texture::Texture;
+
+    fn quad() -> MeshData {
+        MeshData::new(
+            vec![
+                Vec3::new(-1.0, -1.0, 0.0),
+                Vec3::new(1.0, -1.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(-1.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+            material(),
+        )
+        .uvs(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+    }
+
+    #[test]
+    fn test_hit() {
+        let mesh = Mesh::new(quad());
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = mesh.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+
+        assert!((hit_rec.t - 2.0).abs() < 1e-5);
+        assert!(hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!((hit_rec.u - 0.75).abs() < 1e-5);
+        assert!((hit_rec.v - 0.75).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(1.5, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+
+    #[test]
+    fn test_mesh_without_faces() {
+        let mut data = quad();
+        data.indices.clear();
+        let mesh = Mesh::new(data);
+        assert_eq!(mesh.bounding_box(), aabb::EMPTY);
+
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+
+    #[test]
+    fn test_vertex_colors_are_interpolated() {
+        let data = quad().colors(vec![
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ]);
+        let mesh = Mesh::new(data);
+        let ray = Ray::new(Vec3::new(0.0, -0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = mesh.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+
+        let color = Texture::VertexColor.value_at(&hit_rec);
+        assert!((color - Vec3::new(0.5, 0.25, 0.25)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_tangents_follow_uvs() {
+        let mut data = quad();
+        data.compute_tangents();
+
+        for (tangent, bitangent) in data.tangents.iter().zip(data.bitangents.iter()) {
+            assert!(tangent.unit_vector().dot(Vec3::new(1.0, 0.0, 0.0)) > 0.999);
+            assert!(bitangent.unit_vector().dot(Vec3::new(0.0, 1.0, 0.0)) > 0.999);
+        }
+    }
+}
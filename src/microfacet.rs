@@ -0,0 +1,259 @@
+use crate::rng;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+
+/// Anisotropic GGX (Trowbridge-Reitz) distribution of microfacet normals.
+///
+/// All directions are in the local shading frame, with the macro surface
+/// normal along `z`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ggx {
+    pub alpha_x: f32,
+    pub alpha_y: f32,
+}
+
+const MIN_ALPHA: f32 = 1e-3;
+
+impl Ggx {
+    /// Maps perceptual `roughness` and `anisotropy` in `[0, 1]` to the
+    /// distribution's alphas, stretching the highlight along the `x` axis.
+    pub fn from_roughness(roughness: f32, anisotropy: f32) -> Ggx {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        let aspect = (1.0 - 0.9 * anisotropy.clamp(0.0, 1.0)).sqrt();
+
+        Ggx {
+            alpha_x: (alpha / aspect).max(MIN_ALPHA),
+            alpha_y: (alpha * aspect).max(MIN_ALPHA),
+        }
+    }
+
+    pub fn d(&self, m: Vec3) -> f32 {
+        if m.z() <= 0.0 {
+            return 0.0;
+        }
+        let x = m.x() / self.alpha_x;
+        let y = m.y() / self.alpha_y;
+        let t = x * x + y * y + m.z() * m.z();
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * t * t)
+    }
+
+    pub fn lambda(&self, w: Vec3) -> f32 {
+        let z2 = w.z() * w.z();
+        if z2 == 0.0 {
+            return f32::INFINITY;
+        }
+        let a2 = (self.alpha_x * w.x()).powi(2) + (self.alpha_y * w.y()).powi(2);
+
+        ((1.0 + a2 / z2).sqrt() - 1.0) / 2.0
+    }
+
+    pub fn g1(&self, w: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated masking-shadowing term.
+    pub fn g2(&self, wo: Vec3, wi: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Microfacet reflection BSDF without the Fresnel term, multiplied by the
+    /// cosine of `wi`.
+    pub fn reflection(&self, wo: Vec3, wi: Vec3) -> f32 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let wm = (wo + wi).unit_vector();
+
+        self.d(wm) * self.g2(wo, wi) / (4.0 * wo.z())
+    }
+
+    /// Samples a microfacet normal visible from `wo`, following Heitz 2018,
+    /// "Sampling the GGX Distribution of Visible Normals".
+    pub fn sample_visible_normal(&self, wo: Vec3) -> Vec3 {
+        let vh = Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).unit_vector();
+
+        let len_sq = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if len_sq > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / len_sq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        let r = rng::random().sqrt();
+        let phi = 2.0 * PI * rng::random();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        Vec3::new(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(0.0),
+        )
+        .unit_vector()
+    }
+}
+
+/// Unpolarized Fresnel reflectance at a dielectric interface, where `eta` is
+/// the ratio of the incident to the transmitted index of refraction.
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    let rs = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let rp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (rs * rs + rp * rp) / 2.0
+}
+
+fn fresnel_conductor_channel(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    (rs + rp) / 2.0
+}
+
+/// Fresnel reflectance of a conductor with complex index of refraction
+/// `eta + i k`, evaluated per color channel.
+pub fn fresnel_conductor(cos_i: f32, eta: Vec3, k: Vec3) -> Vec3 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+
+    Vec3::new(
+        fresnel_conductor_channel(cos_i, eta.x(), k.x()),
+        fresnel_conductor_channel(cos_i, eta.y(), k.y()),
+        fresnel_conductor_channel(cos_i, eta.z(), k.z()),
+    )
+}
+
+/// Complex index of refraction sampled at red, green and blue wavelengths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComplexIor {
+    pub eta: Vec3,
+    pub k: Vec3,
+}
+
+impl ComplexIor {
+    pub const GOLD: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [0.143, 0.374, 1.442],
+        },
+        k: Vec3 {
+            e: [3.983, 2.385, 1.603],
+        },
+    };
+    pub const SILVER: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [0.155, 0.117, 0.138],
+        },
+        k: Vec3 {
+            e: [4.828, 3.122, 2.147],
+        },
+    };
+    pub const COPPER: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [0.200, 0.924, 1.102],
+        },
+        k: Vec3 {
+            e: [3.912, 2.452, 2.142],
+        },
+    };
+    pub const ALUMINIUM: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [1.657, 0.880, 0.521],
+        },
+        k: Vec3 {
+            e: [9.224, 6.270, 4.837],
+        },
+    };
+    pub const IRON: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [2.911, 2.950, 2.585],
+        },
+        k: Vec3 {
+            e: [3.089, 2.932, 2.767],
+        },
+    };
+    pub const CHROMIUM: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [3.107, 3.182, 2.348],
+        },
+        k: Vec3 {
+            e: [3.314, 3.324, 3.256],
+        },
+    };
+    pub const TITANIUM: ComplexIor = ComplexIor {
+        eta: Vec3 {
+            e: [2.741, 2.541, 2.267],
+        },
+        k: Vec3 {
+            e: [3.814, 3.435, 3.039],
+        },
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresnel_dielectric_normal_incidence() {
+        let f = fresnel_dielectric(1.0, 1.0 / 1.5);
+        assert!((f - 0.04).abs() < 1e-4);
+        assert_eq!(fresnel_dielectric(0.1, 1.5), 1.0);
+    }
+
+    #[test]
+    fn test_fresnel_conductor_matches_dielectric_without_absorption() {
+        let f = fresnel_conductor(0.7, Vec3::new(1.5, 1.5, 1.5), Vec3::zero());
+        assert!((f.x() - fresnel_dielectric(0.7, 1.0 / 1.5)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_visible_normals_face_the_viewer() {
+        let ggx = Ggx::from_roughness(0.7, 0.5);
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        for _ in 0..1000 {
+            let m = ggx.sample_visible_normal(wo);
+            assert!(m.z() >= 0.0);
+            assert!(m.dot(wo) >= -1e-4);
+        }
+    }
+
+    #[test]
+    fn test_ggx_is_normalized() {
+        let ggx = Ggx::from_roughness(0.5, 0.0);
+        let n = 256;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let cos_theta = (i as f32 + 0.5) / n as f32;
+                let phi = 2.0 * PI * (j as f32 + 0.5) / n as f32;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let m = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+                integral += ggx.d(m) * cos_theta * 2.0 * PI / (n * n) as f32;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01);
+    }
+}
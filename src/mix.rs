@@ -0,0 +1,304 @@
+use crate::hittable::HitRecord;
+use crate::material::{self, Material, Scatter, Scatterable};
+use crate::microfacet::{self, Ggx};
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::rng;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// Blends two materials, picking `b` with probability `factor` and `a`
+/// otherwise at every scattering event. Where a material is cut out, the
+/// other one makes up the surface that remains.
+#[derive(Clone)]
+pub struct Mix {
+    pub a: Material,
+    pub b: Material,
+    pub factor: Texture,
+}
+
+impl Mix {
+    pub fn new(a: Material, b: Material, factor: Texture) -> Mix {
+        Mix { a, b, factor }
+    }
+
+    /// Opacity of both materials blended by `factor`, so the surface is only
+    /// partially present where one of them is cut out.
+    pub fn opacity(&self, u: f32, v: f32) -> f32 {
+        let t = self.factor.scalar(u, v).clamp(0.0, 1.0);
+        (1.0 - t) * self.a.opacity(u, v) + t * self.b.opacity(u, v)
+    }
+
+    /// Weight of `b` among the materials present at `(u, v)`, given that the
+    /// surface passed the alpha test there.
+    fn weight(&self, u: f32, v: f32) -> f32 {
+        let t = self.factor.scalar(u, v).clamp(0.0, 1.0);
+        let a = (1.0 - t) * self.a.opacity(u, v);
+        let b = t * self.b.opacity(u, v);
+        if a + b > 0.0 {
+            b / (a + b)
+        } else {
+            t
+        }
+    }
+
+    fn pick(&self, u: f32, v: f32) -> &Material {
+        if rng::random() < self.weight(u, v) {
+            &self.b
+        } else {
+            &self.a
+        }
+    }
+}
+
+impl Scatterable for Mix {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        self.pick(hit_rec.u, hit_rec.v).scatter(ray_in, hit_rec)
+    }
+
+    fn emitted(&self, hit_rec: &HitRecord) -> Vec3 {
+        let t = self.weight(hit_rec.u, hit_rec.v);
+        (1.0 - t) * self.a.emitted(hit_rec) + t * self.b.emitted(hit_rec)
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        let t = self.weight(hit_rec.u, hit_rec.v);
+        (1.0 - t) * self.a.eval(ray_in, hit_rec, direction)
+            + t * self.b.eval(ray_in, hit_rec, direction)
+    }
+
+    fn albedo(&self, hit_rec: &HitRecord) -> Vec3 {
+        let t = self.weight(hit_rec.u, hit_rec.v);
+        (1.0 - t) * self.a.albedo(hit_rec) + t * self.b.albedo(hit_rec)
+    }
+}
+
+/// Clear dielectric coating over another material, such as varnish over wood
+/// or lacquer over paint.
+///
+/// Light is either reflected by the coating, by its Fresnel weight, or passes
+/// through it to scatter off the base. The way back out loses the light that
+/// is reflected internally by the coating, and `absorption` tints the light
+/// by the distance travelled through a coating of `thickness` units.
+#[derive(Clone)]
+pub struct Layered {
+    pub base: Material,
+    pub ior: f32,
+    pub roughness: f32,
+    pub absorption: Vec3,
+    pub thickness: f32,
+}
+
+impl Layered {
+    pub fn new(base: Material, ior: f32) -> Layered {
+        Layered {
+            base,
+            ior,
+            roughness: 0.0,
+            absorption: Vec3::zero(),
+            thickness: 0.0,
+        }
+    }
+
+    pub fn roughness(mut self, roughness: f32) -> Layered {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn absorption(mut self, absorption: Vec3, thickness: f32) -> Layered {
+        self.absorption = absorption;
+        self.thickness = thickness;
+        self
+    }
+
+    /// Length of the path through the coating relative to its thickness, for
+    /// light crossing the surface at `cos` to the normal.
+    fn path_length(&self, cos: f32) -> f32 {
+        let sin2_t = (1.0 - cos * cos) / (self.ior * self.ior);
+        1.0 / (1.0 - sin2_t).max(1e-4).sqrt()
+    }
+}
+
+impl Scatterable for Layered {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        // The coating only exists on the outside of the surface.
+        if !hit_rec.front_face {
+            return self.base.scatter(ray_in, hit_rec);
+        }
+
+        let onb = Onb::from_w(hit_rec.normal);
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+
+        let ggx = Ggx::from_roughness(self.roughness, 0.0);
+        let wm = ggx.sample_visible_normal(wo);
+        if rng::random() < microfacet::fresnel_dielectric(wo.dot(wm), 1.0 / self.ior) {
+            let wi = Vec3::reflect(-wo, wm);
+            if wi.z() <= 0.0 {
+                return None;
+            }
+            let weight = ggx.g2(wo, wi) / ggx.g1(wo);
+
+            return Some(Scatter {
+                attenuation: Vec3::new(weight, weight, weight),
+                ray: Ray::new(hit_rec.point, onb.local(wi)),
+            });
+        }
+
+        let scatter = self.base.scatter(ray_in, hit_rec)?;
+        let cos_o = scatter.ray.direction.unit_vector().dot(hit_rec.normal);
+        if cos_o <= 0.0 {
+            return Some(scatter);
+        }
+
+        let distance = self.thickness * (self.path_length(wo.z()) + self.path_length(cos_o));
+        let transmittance = 1.0 - microfacet::fresnel_dielectric(cos_o, 1.0 / self.ior);
+
+        Some(Scatter {
+            attenuation: scatter.attenuation
+                * material::beer_lambert(self.absorption, distance)
+                * transmittance,
+            ray: scatter.ray,
+        })
+    }
+
+    fn emitted(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.base.emitted(hit_rec)
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        if !hit_rec.front_face {
+            return self.base.eval(ray_in, hit_rec, direction);
+        }
+
+        let onb = Onb::from_w(hit_rec.normal);
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        let wi = onb.to_local(direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Vec3::zero();
+        }
+        let wm = (wo + wi).unit_vector();
+
+        let ggx = Ggx::from_roughness(self.roughness, 0.0);
+        let coat =
+            microfacet::fresnel_dielectric(wo.dot(wm), 1.0 / self.ior) * ggx.reflection(wo, wi);
+
+        // Light reaches the base through the coating on the way in and out.
+        let distance = self.thickness * (self.path_length(wo.z()) + self.path_length(wi.z()));
+        let transmittance = (1.0 - microfacet::fresnel_dielectric(wo.z(), 1.0 / self.ior))
+            * (1.0 - microfacet::fresnel_dielectric(wi.z(), 1.0 / self.ior));
+
+        coat * Vec3::new(1.0, 1.0, 1.0)
+            + self.base.eval(ray_in, hit_rec, direction)
+                * material::beer_lambert(self.absorption, distance)
+                * transmittance
+    }
+
+    fn albedo(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.base.albedo(hit_rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cutout::{AlphaMode, Cutout};
+    use crate::material::Lambertian;
+    use crate::testing::hit_record;
+
+    fn lambertian(albedo: f32) -> Material {
+        Material::Lambertian(Lambertian {
+            albedo: Vec3::new(albedo, albedo, albedo),
+        })
+    }
+
+    #[test]
+    fn test_mix_factor_selects_material() {
+        let mix = Mix::new(lambertian(0.2), lambertian(0.8), Texture::solid(1.0));
+        let material = Material::Mix(Box::new(mix));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        for _ in 0..16 {
+            let scatter = material.scatter(&ray, &hit_record(&material)).unwrap();
+            assert_eq!(scatter.attenuation, Vec3::new(0.8, 0.8, 0.8));
+        }
+    }
+
+    #[test]
+    fn test_mix_blends_alpha() {
+        let hole = Material::Cutout(Box::new(Cutout {
+            material: lambertian(0.5),
+            alpha: Texture::solid(0.0),
+            mode: AlphaMode::Threshold(0.5),
+        }));
+        let mix = |factor: f32| {
+            Material::Mix(Box::new(Mix::new(
+                lambertian(0.5),
+                hole.clone(),
+                Texture::solid(factor),
+            )))
+        };
+
+        assert!((0..100).all(|_| mix(0.0).alpha_test(0.5, 0.5)));
+        assert!((0..100).all(|_| !mix(1.0).alpha_test(0.5, 0.5)));
+
+        let n = 10000;
+        let present = (0..n).filter(|_| mix(0.25).alpha_test(0.5, 0.5)).count();
+        assert!((present as f32 / n as f32 - 0.75).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_mix_picks_present_material() {
+        let hole = Material::Cutout(Box::new(Cutout {
+            material: lambertian(0.8),
+            alpha: Texture::solid(0.0),
+            mode: AlphaMode::Threshold(0.5),
+        }));
+        let mix = Mix::new(lambertian(0.2), hole, Texture::solid(0.5));
+        let material = Material::Mix(Box::new(mix));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = hit_record(&material);
+
+        assert_eq!(material.opacity(0.5, 0.5), 0.5);
+        for _ in 0..16 {
+            let scatter = material.scatter(&ray, &hit_rec).unwrap();
+            assert_eq!(scatter.attenuation, Vec3::new(0.2, 0.2, 0.2));
+        }
+        assert_eq!(material.albedo(&hit_rec), Vec3::new(0.2, 0.2, 0.2));
+        let direction = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(
+            material.eval(&ray, &hit_rec, direction),
+            lambertian(0.2).eval(&ray, &hit_rec, direction)
+        );
+    }
+
+    #[test]
+    fn test_mix_albedo_blends_materials() {
+        let mix = Mix::new(lambertian(0.2), lambertian(0.8), Texture::solid(0.25));
+        let material = Material::Mix(Box::new(mix));
+
+        let albedo = material.albedo(&hit_record(&material));
+        assert!((albedo - Vec3::new(0.35, 0.35, 0.35)).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_layered_conserves_energy() {
+        let material = Material::Layered(Box::new(Layered::new(lambertian(1.0), 1.5)));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let n = 4096;
+        let mut total = 0.0;
+        for _ in 0..n {
+            if let Some(scatter) = material.scatter(&ray, &hit_record(&material)) {
+                let attenuation = scatter.attenuation.x();
+                assert!(attenuation <= 1.0 + 1e-4);
+                total += attenuation;
+            }
+        }
+
+        let mean = total / n as f32;
+        assert!(mean > 0.7 && mean < 1.0);
+    }
+}
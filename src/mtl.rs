@@ -0,0 +1,141 @@
+use crate::image::Image;
+use crate::principled::{MtlParams, Principled};
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Loads the materials of a Wavefront MTL library by name. Texture maps are
+/// read relative to the directory of the library.
+pub fn load(path: &str) -> Result<HashMap<String, Principled>> {
+    let text = fs::read_to_string(path)?;
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    parse(&text, dir)
+}
+
+pub fn parse(text: &str, dir: &Path) -> Result<HashMap<String, Principled>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlParams)> = None;
+
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        if keyword.starts_with('#') {
+            continue;
+        }
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, params)) = current.take() {
+                materials.insert(name, Principled::from_mtl(&params));
+            }
+            let name = args.join(" ");
+            if name.is_empty() {
+                return Err(invalid_data("MTL material has no name"));
+            }
+            current = Some((name, MtlParams::default()));
+            continue;
+        }
+
+        let Some((_, params)) = current.as_mut() else {
+            return Err(invalid_data("MTL statement before newmtl"));
+        };
+        match keyword {
+            "Kd" => params.kd = color(&args)?,
+            "Ks" => params.ks = color(&args)?,
+            "Ke" => params.ke = color(&args)?,
+            "Ns" => params.ns = number(&args)?,
+            "Ni" => params.ni = number(&args)?,
+            "d" => params.d = number(&args)?,
+            "Tr" => params.d = 1.0 - number(&args)?,
+            "Pr" => params.pr = Some(number(&args)?),
+            "Pm" => params.pm = Some(number(&args)?),
+            "Ps" => params.ps = Some(number(&args)?),
+            "Pc" => params.pc = Some(number(&args)?),
+            "Pcr" => params.pcr = Some(number(&args)?),
+            "map_Kd" => params.map_kd = Some(texture(&args, dir)?),
+            "map_Ke" => params.map_ke = Some(texture(&args, dir)?),
+            _ => {}
+        }
+    }
+
+    if let Some((name, params)) = current {
+        materials.insert(name, Principled::from_mtl(&params));
+    }
+    Ok(materials)
+}
+
+fn number(args: &[&str]) -> Result<f32> {
+    args.first()
+        .and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| invalid_data("Invalid number in MTL file"))
+}
+
+/// Reads an RGB triple, or a single value for grey.
+fn color(args: &[&str]) -> Result<Vec3> {
+    let values = args
+        .iter()
+        .take(3)
+        .map(|arg| arg.parse::<f32>())
+        .collect::<std::result::Result<Vec<f32>, _>>()
+        .map_err(|_| invalid_data("Invalid color in MTL file"))?;
+
+    match values[..] {
+        [v] => Ok(Vec3::new(v, v, v)),
+        [r, g, b] => Ok(Vec3::new(r, g, b)),
+        _ => Err(invalid_data("Invalid color in MTL file")),
+    }
+}
+
+/// Loads a color map, whose file name follows any options of the statement.
+fn texture(args: &[&str], dir: &Path) -> Result<Texture> {
+    let file = args
+        .last()
+        .ok_or_else(|| invalid_data("MTL texture map has no file"))?;
+    let path = dir.join(file);
+    let image = Image::load(&path.to_string_lossy())?;
+
+    Ok(Texture::Image {
+        image: Arc::new(image),
+        srgb: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_library() {
+        let mtl = "# two materials\n\
+            newmtl glass\nKd 0.1 0.2 0.3\nNi 1.45\nd 0.25\n\n\
+            newmtl rough metal\nKd 0.9\nPm 1\nPr 0.6\nillum 2\n";
+        let materials = parse(mtl, Path::new("")).unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let glass = &materials["glass"];
+        assert_eq!(glass.base_color.scalar(0.0, 0.0), 0.1);
+        assert_eq!(glass.ior, 1.45);
+        assert_eq!(glass.transmission.scalar(0.0, 0.0), 0.75);
+
+        let metal = &materials["rough metal"];
+        assert_eq!(metal.metallic.scalar(0.0, 0.0), 1.0);
+        assert_eq!(metal.roughness.scalar(0.0, 0.0), 0.6);
+    }
+
+    #[test]
+    fn test_invalid_library_is_rejected() {
+        assert!(parse("Kd 1 1 1\n", Path::new("")).is_err());
+        assert!(parse("newmtl a\nKd 1 x 1\n", Path::new("")).is_err());
+        assert!(parse("newmtl a\nmap_Kd missing.ppm\n", Path::new("")).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use crate::vec3::Vec3;
+
+/// Orthonormal basis with `w` as the "up" axis of the local frame.
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Builds a basis around `n` whose `u` axis follows the latitude direction
+    /// relative to world up, so anisotropic materials have a stable orientation.
+    pub fn from_w(n: Vec3) -> Onb {
+        let w = n.unit_vector();
+        let up = if w.y().abs() > 0.999 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        let u = up.cross(w).unit_vector();
+        let v = w.cross(u);
+
+        Onb { u, v, w }
+    }
+
+    /// Builds a basis around `n` with `u` aligned to `tangent` projected onto
+    /// the plane perpendicular to `n`, falling back to `from_w` when the
+    /// tangent is degenerate.
+    pub fn from_normal_tangent(n: Vec3, tangent: Vec3) -> Onb {
+        let w = n.unit_vector();
+        let projected = tangent - tangent.dot(w) * w;
+        if projected.length_squared() < 1e-12 {
+            return Onb::from_w(w);
+        }
+        let u = projected.unit_vector();
+        let v = w.cross(u);
+
+        Onb { u, v, w }
+    }
+
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+
+    pub fn to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(a.dot(self.u), a.dot(self.v), a.dot(self.w))
+    }
+}
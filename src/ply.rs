@@ -0,0 +1,414 @@
+use crate::material::Material;
+use crate::mesh::MeshData;
+use crate::texture;
+use crate::vec3::Vec3;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<ScalarType> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::I8),
+            "uchar" | "uint8" => Ok(ScalarType::U8),
+            "short" | "int16" => Ok(ScalarType::I16),
+            "ushort" | "uint16" => Ok(ScalarType::U16),
+            "int" | "int32" => Ok(ScalarType::I32),
+            "uint" | "uint32" => Ok(ScalarType::U32),
+            "float" | "float32" => Ok(ScalarType::F32),
+            "double" | "float64" => Ok(ScalarType::F64),
+            _ => Err(invalid_data("Unknown PLY property type")),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// Maximum value of unsigned integer types, which normalizes colors.
+    fn color_scale(self) -> f32 {
+        match self {
+            ScalarType::U8 => 255.0,
+            ScalarType::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar(String, ScalarType),
+    List(String, ScalarType, ScalarType),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// Offset of the first byte after the header.
+    end: usize,
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Header> {
+    const END: &[u8] = b"end_header";
+    let end_pos = bytes
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or_else(|| invalid_data("Missing PLY end_header"))?;
+    let mut end = end_pos + END.len();
+    if bytes.get(end) == Some(&b'\r') {
+        end += 1;
+    }
+    if bytes.get(end) == Some(&b'\n') {
+        end += 1;
+    }
+
+    let text =
+        std::str::from_utf8(&bytes[..end_pos]).map_err(|_| invalid_data("Invalid PLY header"))?;
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(invalid_data("Not a PLY file"));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data("Invalid PLY element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("PLY property outside of an element"))?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    ScalarType::parse(count)?,
+                    ScalarType::parse(item)?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("PLY property outside of an element"))?
+                .properties
+                .push(Property::Scalar(name.to_string(), ScalarType::parse(ty)?)),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(invalid_data("Invalid PLY header line")),
+        }
+    }
+
+    Ok(Header {
+        format: format.ok_or_else(|| invalid_data("Missing PLY format"))?,
+        elements,
+        end,
+    })
+}
+
+struct BodyReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl<'a> BodyReader<'a> {
+    fn token(&mut self) -> Result<&'a str> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(invalid_data("Unexpected end of PLY data"));
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .map_err(|_| invalid_data("Invalid PLY data"))
+    }
+
+    fn read(&mut self, ty: ScalarType) -> Result<f64> {
+        if self.format == Format::Ascii {
+            return self
+                .token()?
+                .parse()
+                .map_err(|_| invalid_data("Invalid number in PLY data"));
+        }
+
+        let size = ty.size();
+        if self.pos + size > self.bytes.len() {
+            return Err(invalid_data("Unexpected end of PLY data"));
+        }
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(&self.bytes[self.pos..self.pos + size]);
+        self.pos += size;
+        if self.format == Format::BinaryBigEndian {
+            raw[..size].reverse();
+        }
+
+        Ok(match ty {
+            ScalarType::I8 => raw[0] as i8 as f64,
+            ScalarType::U8 => raw[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            ScalarType::F64 => f64::from_le_bytes(raw),
+        })
+    }
+}
+
+/// Position of the first scalar property with one of `names`.
+fn find(element: &Element, names: &[&str]) -> Option<(usize, ScalarType)> {
+    element
+        .properties
+        .iter()
+        .enumerate()
+        .find_map(|(i, property)| match property {
+            Property::Scalar(name, ty) if names.contains(&name.as_str()) => Some((i, *ty)),
+            _ => None,
+        })
+}
+
+/// Loads a PLY mesh in ASCII or binary format. Polygons are triangulated as
+/// fans. Normals, texture coordinates and vertex colors are read when present;
+/// integer colors are taken to be sRGB encoded and converted to linear.
+pub fn load(path: &str, material: Material) -> Result<MeshData> {
+    let bytes = fs::read(path)?;
+    parse(&bytes, material)
+}
+
+pub fn parse(bytes: &[u8], material: Material) -> Result<MeshData> {
+    let header = parse_header(bytes)?;
+    let mut reader = BodyReader {
+        bytes,
+        pos: header.end,
+        format: header.format,
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    for element in header.elements.iter() {
+        let xyz = [&["x"], &["y"], &["z"]].map(|names| find(element, names));
+        let normal = [&["nx"], &["ny"], &["nz"]].map(|names| find(element, names));
+        let uv = [&["u", "s", "texture_u"][..], &["v", "t", "texture_v"][..]]
+            .map(|names| find(element, names));
+        let color = [
+            &["red", "diffuse_red", "r"][..],
+            &["green", "diffuse_green", "g"][..],
+            &["blue", "diffuse_blue", "b"][..],
+        ]
+        .map(|names| find(element, names));
+
+        for _ in 0..element.count {
+            let mut scalars = vec![0.0; element.properties.len()];
+            let mut face = Vec::new();
+
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar(_, ty) => scalars[i] = reader.read(*ty)?,
+                    Property::List(name, count_ty, item_ty) => {
+                        let count = reader.read(*count_ty)? as usize;
+                        let is_face = element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index");
+                        for _ in 0..count {
+                            let value = reader.read(*item_ty)?;
+                            if is_face {
+                                let in_range = (0.0..=u32::MAX as f64).contains(&value);
+                                if !in_range || value.fract() != 0.0 {
+                                    return Err(invalid_data("Invalid PLY vertex index"));
+                                }
+                                face.push(value as u32);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if element.name == "vertex" {
+                let get = |p: Option<(usize, ScalarType)>| p.map(|(i, _)| scalars[i] as f32);
+                let vec = |p: [Option<(usize, ScalarType)>; 3]| match p.map(get) {
+                    [Some(x), Some(y), Some(z)] => Some(Vec3::new(x, y, z)),
+                    _ => None,
+                };
+
+                positions
+                    .push(vec(xyz).ok_or_else(|| invalid_data("PLY vertex without position"))?);
+                if let Some(n) = vec(normal) {
+                    normals.push(n);
+                }
+                if let [Some(u), Some(v)] = uv.map(get) {
+                    uvs.push((u, v));
+                }
+                if let Some(c) = vec(color) {
+                    let scale = color[0].map_or(1.0, |(_, ty)| ty.color_scale());
+                    colors.push(if scale > 1.0 {
+                        Vec3::new(
+                            texture::srgb_to_linear(c.x() / scale),
+                            texture::srgb_to_linear(c.y() / scale),
+                            texture::srgb_to_linear(c.z() / scale),
+                        )
+                    } else {
+                        c
+                    });
+                }
+            } else if face.len() >= 3 {
+                for k in 1..face.len() - 1 {
+                    indices.push([face[0], face[k], face[k + 1]]);
+                }
+            }
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(invalid_data("PLY file has no faces"));
+    }
+    if indices
+        .iter()
+        .flatten()
+        .any(|i| *i as usize >= positions.len())
+    {
+        return Err(invalid_data("PLY face index out of range"));
+    }
+
+    Ok(MeshData::new(positions, indices, material)
+        .normals(normals)
+        .uvs(uvs)
+        .colors(colors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    const HEADER: &str = "element vertex 4\n\
+        property float x\nproperty float y\nproperty float z\n\
+        property uchar red\nproperty uchar green\nproperty uchar blue\n\
+        element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    #[test]
+    fn test_parse_ascii_with_colors() {
+        let ply = format!(
+            "ply\nformat ascii 1.0\ncomment test\n{}\
+             0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n\
+             4 0 1 2 3\n",
+            HEADER
+        );
+        let data = parse(ply.as_bytes(), material()).unwrap();
+
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.positions[2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(data.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(data.colors[0], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(data.colors[3], Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    fn binary(format: &str, encode: fn(f32) -> [u8; 4], encode_int: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut bytes = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        for (p, c) in [
+            ([0.0, 0.0, 0.0], [255, 0, 0]),
+            ([1.0, 0.0, 0.0], [0, 255, 0]),
+            ([1.0, 1.0, 0.0], [0, 0, 255]),
+            ([0.0, 1.0, 0.0], [0, 0, 0]),
+        ] {
+            for x in p {
+                bytes.extend_from_slice(&encode(x));
+            }
+            bytes.extend_from_slice(&c);
+        }
+        bytes.push(3);
+        for i in [2, 3, 0] {
+            bytes.extend_from_slice(&encode_int(i));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        for bytes in [
+            binary("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes),
+            binary("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes),
+        ] {
+            let data = parse(&bytes, material()).unwrap();
+            assert_eq!(data.positions[1], Vec3::new(1.0, 0.0, 0.0));
+            assert_eq!(data.colors[2], Vec3::new(0.0, 0.0, 1.0));
+            assert_eq!(data.indices, vec![[2, 3, 0]]);
+        }
+    }
+
+    #[test]
+    fn test_truncated_file_is_rejected() {
+        let mut bytes = binary("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        bytes.truncate(bytes.len() - 2);
+        assert!(parse(&bytes, material()).is_err());
+    }
+
+    #[test]
+    fn test_file_without_faces_is_rejected() {
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\n\
+            property float x\nproperty float y\nproperty float z\nend_header\n\
+            0 0 0\n1 0 0\n0 1 0\n";
+        let error = parse(ply.as_bytes(), material()).err().map(|e| e.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_invalid_face_indices_are_rejected() {
+        for indices in ["0 -1 2", "0 1.5 2", "0 nan 2", "0 inf 2"] {
+            let ply = format!(
+                "ply\nformat ascii 1.0\nelement vertex 3\n\
+                 property float x\nproperty float y\nproperty float z\n\
+                 element face 1\nproperty list uchar float vertex_indices\nend_header\n\
+                 0 0 0\n1 0 0\n0 1 0\n3 {}\n",
+                indices
+            );
+            let error = parse(ply.as_bytes(), material()).err().map(|e| e.kind());
+            assert_eq!(error, Some(ErrorKind::InvalidData));
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use crate::aabb::{self, Aabb};
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+use std::io::{Error, ErrorKind, Result};
+
+/// Points per leaf of the acceleration structure.
+const LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointShape {
+    Sphere,
+    /// Disk facing the ray, cheaper than a sphere and enough for dense
+    /// scans seen from afar.
+    Splat,
+}
+
+fn invalid_input(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg.to_string())
+}
+
+/// Points of a cloud, stored as flat arrays. Colors and radii are optional
+/// and either empty or the same length as `positions`.
+pub struct PointData {
+    pub positions: Vec<Vec3>,
+    /// Linear RGB point colors, read by `Texture::VertexColor`.
+    pub colors: Vec<Vec3>,
+    pub radii: Vec<f32>,
+    /// Radius of every point when `radii` is empty.
+    pub radius: f32,
+    pub shape: PointShape,
+    pub material: Material,
+}
+
+impl PointData {
+    pub fn new(positions: Vec<Vec3>, radius: f32, material: Material) -> PointData {
+        PointData {
+            positions,
+            colors: Vec::new(),
+            radii: Vec::new(),
+            radius,
+            shape: PointShape::Sphere,
+            material,
+        }
+    }
+
+    pub fn colors(mut self, colors: Vec<Vec3>) -> PointData {
+        self.colors = colors;
+        self
+    }
+
+    pub fn radii(mut self, radii: Vec<f32>) -> PointData {
+        self.radii = radii;
+        self
+    }
+
+    pub fn shape(mut self, shape: PointShape) -> PointData {
+        self.shape = shape;
+        self
+    }
+
+    fn radius(&self, i: usize) -> f32 {
+        if self.radii.is_empty() {
+            self.radius
+        } else {
+            self.radii[i]
+        }
+    }
+
+    fn bbox(&self, i: usize) -> Aabb {
+        let r = self.radius(i);
+        let r = Vec3::new(r, r, r);
+        Aabb::from_points(self.positions[i] - r, self.positions[i] + r)
+    }
+
+    /// Reorders all per-point arrays by `order`.
+    fn permute(&mut self, order: &[u32]) {
+        fn apply<T: Copy>(values: &mut Vec<T>, order: &[u32]) {
+            if !values.is_empty() {
+                *values = order.iter().map(|i| values[*i as usize]).collect();
+            }
+        }
+        apply(&mut self.positions, order);
+        apply(&mut self.colors, order);
+        apply(&mut self.radii, order);
+    }
+}
+
+/// Node of the flattened BVH. The left child of an inner node directly
+/// follows it, and `first` is the index of the right child; leaves cover
+/// points `first..first + count`.
+struct Node {
+    bbox: Aabb,
+    first: u32,
+    count: u32,
+}
+
+/// Point cloud with its own BVH over the points, so millions of them can be
+/// added to the world as a single object.
+pub struct PointCloud {
+    data: PointData,
+    nodes: Vec<Node>,
+}
+
+impl PointCloud {
+    /// Builds the cloud, failing if it has no points or if its colors or
+    /// radii don't match the number of points.
+    pub fn new(mut data: PointData) -> Result<PointCloud> {
+        if data.positions.is_empty() {
+            return Err(invalid_input("Point cloud has no points"));
+        }
+        let count = data.positions.len();
+        if !data.colors.is_empty() && data.colors.len() != count {
+            return Err(invalid_input("Point cloud colors don't match its points"));
+        }
+        if !data.radii.is_empty() && data.radii.len() != count {
+            return Err(invalid_input("Point cloud radii don't match its points"));
+        }
+
+        let mut order: Vec<u32> = (0..data.positions.len() as u32).collect();
+        let mut nodes = Vec::new();
+        PointCloud::build(&data, &mut order, 0, &mut nodes);
+        data.permute(&order);
+
+        Ok(PointCloud { data, nodes })
+    }
+
+    /// Builds the subtree over `order` by median splits along the longest
+    /// axis, with `offset` the position of `order` in the full point list.
+    fn build(data: &PointData, order: &mut [u32], offset: usize, nodes: &mut Vec<Node>) {
+        let bbox = order.iter().fold(aabb::EMPTY, |bbox, i| {
+            Aabb::enclosing(bbox, data.bbox(*i as usize))
+        });
+
+        let index = nodes.len();
+        nodes.push(Node {
+            bbox,
+            first: offset as u32,
+            count: order.len() as u32,
+        });
+        if order.len() <= LEAF_SIZE {
+            return;
+        }
+
+        let axis = bbox.longest_axis();
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |a, b| {
+            let a = data.positions[*a as usize].e[axis];
+            let b = data.positions[*b as usize].e[axis];
+            a.total_cmp(&b)
+        });
+
+        let (left, right) = order.split_at_mut(mid);
+        PointCloud::build(data, left, offset, nodes);
+        nodes[index].first = nodes.len() as u32;
+        nodes[index].count = 0;
+        PointCloud::build(data, right, offset + mid, nodes);
+    }
+
+    fn hit_point(&self, ray: &Ray, ray_t: Interval, i: usize) -> Option<f32> {
+        let center = self.data.positions[i];
+        let radius = self.data.radius(i);
+        let oc = center - ray.origin;
+        let a = ray.direction.length_squared();
+        let h = ray.direction.dot(oc);
+
+        match self.data.shape {
+            PointShape::Sphere => {
+                let c = oc.length_squared() - radius * radius;
+                let discriminant = h * h - a * c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let sqrtd = discriminant.sqrt();
+                [(h - sqrtd) / a, (h + sqrtd) / a]
+                    .into_iter()
+                    .find(|t| ray_t.surrounds(*t))
+            }
+            PointShape::Splat => {
+                let t = h / a;
+                let inside = (ray.point_at(t) - center).length_squared() <= radius * radius;
+                (inside && ray_t.surrounds(t)).then_some(t)
+            }
+        }
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32, i: usize) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let center = self.data.positions[i];
+        let radius = self.data.radius(i);
+
+        let (outward_normal, u, v, tangent, bitangent) = match self.data.shape {
+            PointShape::Sphere => {
+                let n = (point - center) / radius;
+                let (u, tangent) = hittable::azimuth(n);
+                let v = (-n.y()).clamp(-1.0, 1.0).acos() / PI;
+                (n, u, v, radius * tangent, n.cross(tangent))
+            }
+            PointShape::Splat => {
+                let frame = Onb::from_w(-ray.direction);
+                let local = frame.to_local(point - center) / (2.0 * radius);
+                (frame.w, local.x() + 0.5, local.y() + 0.5, frame.u, frame.v)
+            }
+        };
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.data.material,
+            t,
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_color: if self.data.colors.is_empty() {
+                Vec3::new(1.0, 1.0, 1.0)
+            } else {
+                self.data.colors[i]
+            },
+            front_face,
+        }
+    }
+}
+
+impl Hittable for PointCloud {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mut closest: Option<(f32, usize)> = None;
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let max = closest.map_or(ray_t.max, |(t, _)| t);
+            if !node.bbox.hit(ray, Interval::new(ray_t.min, max)) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(index + 1);
+                continue;
+            }
+
+            let first = node.first as usize;
+            for i in first..first + node.count as usize {
+                let max = closest.map_or(ray_t.max, |(t, _)| t);
+                if let Some(t) = self.hit_point(ray, Interval::new(ray_t.min, max), i) {
+                    closest = Some((t, i));
+                }
+            }
+        }
+
+        let (t, i) = closest?;
+        let hit_rec = self.hit_record(ray, t, i);
+        self.data
+            .material
+            .alpha_test(hit_rec.u, hit_rec.v)
+            .then_some(hit_rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes[0].bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+    use crate::sphere::Sphere;
+    use crate::testing::material;
+
+    #[test]
+    fn test_matches_spheres() {
+        let positions: Vec<Vec3> = (0..500)
+            .map(|_| Vec3::random_in_range(-10.0, 10.0))
+            .collect();
+        let radii: Vec<f32> = (0..500).map(|_| rng::random_in_range(0.1, 0.5)).collect();
+        let colors: Vec<Vec3> = (0..500).map(|_| Vec3::random()).collect();
+
+        let spheres: Vec<Box<dyn Hittable>> = positions
+            .iter()
+            .zip(radii.iter())
+            .map(|(p, r)| {
+                let sphere: Box<dyn Hittable> = Box::new(Sphere::new(*p, *r, material()));
+                sphere
+            })
+            .collect();
+        let cloud = PointCloud::new(
+            PointData::new(positions.clone(), 0.0, material())
+                .radii(radii.clone())
+                .colors(colors.clone()),
+        )
+        .unwrap();
+
+        for _ in 0..1000 {
+            let ray = Ray::new(
+                Vec3::random_in_range(-20.0, 20.0),
+                Vec3::random_unit_vector(),
+            );
+            let ray_t = Interval::new(0.001, f32::INFINITY);
+            let expected = spheres.hit(&ray, ray_t);
+            let hit_rec = cloud.hit(&ray, ray_t);
+            assert_eq!(hit_rec.map(|h| h.t), expected.map(|h| h.t));
+
+            if let Some(hit_rec) = hit_rec {
+                // The color belongs to a point whose surface passes through
+                // the hit.
+                assert!((0..500).any(|i| {
+                    ((positions[i] - hit_rec.point).length() - radii[i]).abs() < 1e-3
+                        && hit_rec.vertex_color == colors[i]
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_splat_faces_ray() {
+        let cloud = PointCloud::new(
+            PointData::new(vec![Vec3::new(0.0, 0.0, -5.0)], 0.5, material())
+                .shape(PointShape::Splat),
+        )
+        .unwrap();
+        let ray = Ray::new(Vec3::new(0.3, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = cloud
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert_eq!(hit_rec.t, 5.0);
+        assert_eq!(hit_rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        // The splat's u axis is +x for a ray along -z.
+        assert_eq!(hit_rec.tangent, Vec3::new(1.0, 0.0, 0.0));
+        assert!((hit_rec.u - 0.8).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.6, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cloud
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+
+    #[test]
+    fn test_mismatched_data_is_rejected() {
+        let positions = vec![Vec3::zero(), Vec3::new(1.0, 0.0, 0.0)];
+        let error = |data: PointData| PointCloud::new(data).err().map(|e| e.kind());
+
+        assert_eq!(
+            error(PointData::new(Vec::new(), 0.5, material())),
+            Some(ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            error(PointData::new(positions.clone(), 0.5, material()).radii(vec![0.5])),
+            Some(ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            error(PointData::new(positions, 0.5, material()).colors(vec![Vec3::zero(); 3])),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use crate::hittable::HitRecord;
+use crate::material::{Scatter, Scatterable};
+use crate::microfacet::{self, Ggx};
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::rng;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+
+const CLEARCOAT_IOR: f32 = 1.5;
+
+/// Disney-style "principled" material combining a diffuse base, a specular
+/// layer, metal, glass, sheen, clearcoat and emission in a single model.
+///
+/// Lobes are picked stochastically per scattering event: clearcoat first, by
+/// its Fresnel weight, then metal by `metallic`, glass by `transmission`, and
+/// finally the specular/diffuse dielectric base.
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Texture,
+    pub metallic: Texture,
+    pub roughness: Texture,
+    pub specular: f32,
+    pub anisotropy: f32,
+    pub sheen: Texture,
+    pub sheen_tint: f32,
+    pub clearcoat: Texture,
+    pub clearcoat_roughness: f32,
+    pub transmission: Texture,
+    pub ior: f32,
+    pub emission: Texture,
+    pub emission_strength: f32,
+}
+
+/// Parameters of a Wavefront MTL material, including the common PBR extension
+/// (`Pr`, `Pm`, `Ps`, `Pc`, `Pcr`).
+#[derive(Clone)]
+pub struct MtlParams {
+    pub kd: Vec3,
+    pub ks: Vec3,
+    pub ke: Vec3,
+    pub ns: f32,
+    pub ni: f32,
+    pub d: f32,
+    pub map_kd: Option<Texture>,
+    pub map_ke: Option<Texture>,
+    pub pr: Option<f32>,
+    pub pm: Option<f32>,
+    pub ps: Option<f32>,
+    pub pc: Option<f32>,
+    pub pcr: Option<f32>,
+}
+
+impl Default for MtlParams {
+    fn default() -> Self {
+        MtlParams {
+            kd: Vec3::new(0.8, 0.8, 0.8),
+            ks: Vec3::zero(),
+            ke: Vec3::zero(),
+            ns: 0.0,
+            ni: 1.0,
+            d: 1.0,
+            map_kd: None,
+            map_ke: None,
+            pr: None,
+            pm: None,
+            ps: None,
+            pc: None,
+            pcr: None,
+        }
+    }
+}
+
+fn schlick_weight(cos: f32) -> f32 {
+    (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
+
+fn schlick(f0: Vec3, cos: f32) -> Vec3 {
+    f0 + schlick_weight(cos) * (Vec3::new(1.0, 1.0, 1.0) - f0)
+}
+
+pub(crate) fn luminance(color: Vec3) -> f32 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+impl Principled {
+    pub fn new(base_color: Texture) -> Principled {
+        Principled {
+            base_color,
+            metallic: Texture::solid(0.0),
+            roughness: Texture::solid(0.5),
+            specular: 0.5,
+            anisotropy: 0.0,
+            sheen: Texture::solid(0.0),
+            sheen_tint: 0.5,
+            clearcoat: Texture::solid(0.0),
+            clearcoat_roughness: 0.03,
+            transmission: Texture::solid(0.0),
+            ior: 1.5,
+            emission: Texture::solid(0.0),
+            emission_strength: 1.0,
+        }
+    }
+
+    /// Builds the material from glTF metallic-roughness parameters. As in glTF,
+    /// metalness is read from the blue and roughness from the green channel of
+    /// `metallic_roughness`, and both are multiplied by their factors.
+    pub fn from_metallic_roughness(
+        base_color: Texture,
+        metallic_roughness: Option<Texture>,
+        metallic_factor: f32,
+        roughness_factor: f32,
+    ) -> Principled {
+        let (metallic, roughness) = match metallic_roughness {
+            Some(texture) => (
+                Texture::Scale(
+                    Box::new(Texture::Channel(Box::new(texture.clone()), 2)),
+                    Vec3::new(metallic_factor, metallic_factor, metallic_factor),
+                ),
+                Texture::Scale(
+                    Box::new(Texture::Channel(Box::new(texture), 1)),
+                    Vec3::new(roughness_factor, roughness_factor, roughness_factor),
+                ),
+            ),
+            None => (
+                Texture::solid(metallic_factor),
+                Texture::solid(roughness_factor),
+            ),
+        };
+
+        Principled {
+            metallic,
+            roughness,
+            ..Principled::new(base_color)
+        }
+    }
+
+    /// Builds the material from MTL parameters. Without the PBR extension the
+    /// roughness is derived from the Phong exponent `Ns` and the specular level
+    /// from the index of refraction `Ni`.
+    pub fn from_mtl(mtl: &MtlParams) -> Principled {
+        let base_color = match &mtl.map_kd {
+            Some(texture) => Texture::Scale(Box::new(texture.clone()), mtl.kd),
+            None => Texture::Solid(mtl.kd),
+        };
+        let emission = match &mtl.map_ke {
+            Some(texture) => Texture::Scale(Box::new(texture.clone()), mtl.ke),
+            None => Texture::Solid(mtl.ke),
+        };
+
+        let roughness = mtl
+            .pr
+            .unwrap_or_else(|| (2.0 / (mtl.ns.max(0.0) + 2.0)).powf(0.25));
+        let ior = if mtl.ni > 1.0 { mtl.ni } else { 1.5 };
+        let specular = if mtl.pm.is_none() && luminance(mtl.ks) <= 0.0 {
+            0.0
+        } else {
+            (((ior - 1.0) / (ior + 1.0)).powi(2) / 0.08).min(1.0)
+        };
+
+        Principled {
+            base_color,
+            metallic: Texture::solid(mtl.pm.unwrap_or(0.0)),
+            roughness: Texture::solid(roughness),
+            specular,
+            sheen: Texture::solid(mtl.ps.unwrap_or(0.0)),
+            clearcoat: Texture::solid(mtl.pc.unwrap_or(0.0)),
+            clearcoat_roughness: mtl.pcr.unwrap_or(0.03),
+            transmission: Texture::solid((1.0 - mtl.d).clamp(0.0, 1.0)),
+            ior,
+            emission,
+            ..Principled::new(Texture::solid(0.0))
+        }
+    }
+
+    /// Sheen of strength `sheen` added to the diffuse lobe, with `cos_d` the
+    /// cosine between the light and the half vector.
+    fn sheen(&self, sheen: f32, base_color: Vec3, cos_d: f32) -> Vec3 {
+        if sheen <= 0.0 {
+            return Vec3::zero();
+        }
+        let tint = if luminance(base_color) > 0.0 {
+            base_color / luminance(base_color)
+        } else {
+            Vec3::new(1.0, 1.0, 1.0)
+        };
+        let sheen_color =
+            (1.0 - self.sheen_tint) * Vec3::new(1.0, 1.0, 1.0) + self.sheen_tint * tint;
+
+        sheen * schlick_weight(cos_d) * sheen_color
+    }
+
+    fn scatter_specular(
+        ggx: &Ggx,
+        onb: &Onb,
+        wo: Vec3,
+        hit_rec: &HitRecord,
+        fresnel: impl Fn(f32) -> Vec3,
+    ) -> Option<Scatter> {
+        let wm = ggx.sample_visible_normal(wo);
+        let wi = Vec3::reflect(-wo, wm);
+        if wi.z() <= 0.0 {
+            return None;
+        }
+
+        Some(Scatter {
+            attenuation: fresnel(wo.dot(wm)) * (ggx.g2(wo, wi) / ggx.g1(wo)),
+            ray: Ray::new(hit_rec.point, onb.local(wi)),
+        })
+    }
+
+    fn scatter_transmission(
+        &self,
+        ggx: &Ggx,
+        onb: &Onb,
+        wo: Vec3,
+        hit_rec: &HitRecord,
+        base_color: Vec3,
+    ) -> Option<Scatter> {
+        let refraction_ratio = if hit_rec.front_face {
+            1.0 / self.ior
+        } else {
+            self.ior
+        };
+        let wm = ggx.sample_visible_normal(wo);
+        let fresnel = microfacet::fresnel_dielectric(wo.dot(wm), refraction_ratio);
+
+        let reflected = rng::random() < fresnel;
+        let (wi, tint) = if reflected {
+            (Vec3::reflect(-wo, wm), Vec3::new(1.0, 1.0, 1.0))
+        } else if hit_rec.front_face {
+            (Vec3::refract(-wo, wm, refraction_ratio), base_color)
+        } else {
+            (
+                Vec3::refract(-wo, wm, refraction_ratio),
+                Vec3::new(1.0, 1.0, 1.0),
+            )
+        };
+        if reflected != (wi.z() > 0.0) {
+            return None;
+        }
+
+        Some(Scatter {
+            attenuation: tint * (ggx.g2(wo, wi) / ggx.g1(wo)),
+            ray: Ray::new(hit_rec.point, onb.local(wi)),
+        })
+    }
+}
+
+impl Scatterable for Principled {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        let base_color = self.base_color.value_at(hit_rec);
+        let metallic = self.metallic.scalar(hit_rec.u, hit_rec.v).clamp(0.0, 1.0);
+        let roughness = self.roughness.scalar(hit_rec.u, hit_rec.v);
+        let transmission = self
+            .transmission
+            .scalar(hit_rec.u, hit_rec.v)
+            .clamp(0.0, 1.0);
+        let clearcoat = self.clearcoat.scalar(hit_rec.u, hit_rec.v).clamp(0.0, 1.0);
+
+        let onb = Onb::from_w(hit_rec.normal);
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+
+        let ggx = Ggx::from_roughness(roughness, self.anisotropy);
+
+        // Rays inside a transmissive object can only leave through the glass.
+        if !hit_rec.front_face && transmission > 0.0 {
+            return self.scatter_transmission(&ggx, &onb, wo, hit_rec, base_color);
+        }
+
+        if clearcoat > 0.0 {
+            let coat = clearcoat * microfacet::fresnel_dielectric(wo.z(), 1.0 / CLEARCOAT_IOR);
+            if rng::random() < coat {
+                let coat_ggx = Ggx::from_roughness(self.clearcoat_roughness, 0.0);
+                return Principled::scatter_specular(&coat_ggx, &onb, wo, hit_rec, |_| {
+                    Vec3::new(1.0, 1.0, 1.0)
+                });
+            }
+        }
+
+        if rng::random() < metallic {
+            return Principled::scatter_specular(&ggx, &onb, wo, hit_rec, |cos| {
+                schlick(base_color, cos)
+            });
+        }
+
+        if rng::random() < transmission {
+            return self.scatter_transmission(&ggx, &onb, wo, hit_rec, base_color);
+        }
+
+        let f0 = 0.08 * self.specular;
+        let wm = ggx.sample_visible_normal(wo);
+        let specular = f0 + (1.0 - f0) * schlick_weight(wo.dot(wm));
+        if rng::random() < specular {
+            let wi = Vec3::reflect(-wo, wm);
+            if wi.z() <= 0.0 {
+                return None;
+            }
+            let weight = ggx.g2(wo, wi) / ggx.g1(wo);
+
+            return Some(Scatter {
+                attenuation: Vec3::new(weight, weight, weight),
+                ray: Ray::new(hit_rec.point, onb.local(wi)),
+            });
+        }
+
+        let mut direction = hit_rec.normal + Vec3::random_unit_vector();
+        if direction.near_zero() {
+            direction = hit_rec.normal;
+        }
+
+        let half = (direction.unit_vector() + onb.local(wo)).unit_vector();
+        let cos_d = direction.unit_vector().dot(half);
+        let sheen = self.sheen.scalar(hit_rec.u, hit_rec.v);
+
+        Some(Scatter {
+            attenuation: base_color + self.sheen(sheen, base_color, cos_d),
+            ray: Ray::new(hit_rec.point, direction),
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        let transmission = self
+            .transmission
+            .scalar(hit_rec.u, hit_rec.v)
+            .clamp(0.0, 1.0);
+        if !hit_rec.front_face && transmission > 0.0 {
+            return Vec3::zero();
+        }
+
+        let onb = Onb::from_w(hit_rec.normal);
+        let wo = onb.to_local(-ray_in.direction.unit_vector());
+        let wi = onb.to_local(direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Vec3::zero();
+        }
+        let wm = (wo + wi).unit_vector();
+
+        let base_color = self.base_color.value_at(hit_rec);
+        let metallic = self.metallic.scalar(hit_rec.u, hit_rec.v).clamp(0.0, 1.0);
+        let roughness = self.roughness.scalar(hit_rec.u, hit_rec.v);
+        let clearcoat = self.clearcoat.scalar(hit_rec.u, hit_rec.v).clamp(0.0, 1.0);
+        let sheen = self.sheen.scalar(hit_rec.u, hit_rec.v);
+        let ggx = Ggx::from_roughness(roughness, self.anisotropy);
+        let reflection = ggx.reflection(wo, wi);
+
+        // Lobes are weighted by the probabilities with which `scatter` picks
+        // them.
+        let coat = clearcoat * microfacet::fresnel_dielectric(wo.z(), 1.0 / CLEARCOAT_IOR);
+        let coat_ggx = Ggx::from_roughness(self.clearcoat_roughness, 0.0);
+        let metal = schlick(base_color, wo.dot(wm)) * reflection;
+
+        let f0 = 0.08 * self.specular;
+        let specular = f0 + (1.0 - f0) * schlick_weight(wo.dot(wm));
+        let diffuse = (base_color + self.sheen(sheen, base_color, wi.dot(wm))) * (wi.z() / PI);
+        let dielectric =
+            specular * reflection * Vec3::new(1.0, 1.0, 1.0) + (1.0 - specular) * diffuse;
+
+        coat * coat_ggx.reflection(wo, wi) * Vec3::new(1.0, 1.0, 1.0)
+            + (1.0 - coat)
+                * (metallic * metal + (1.0 - metallic) * (1.0 - transmission) * dielectric)
+    }
+
+    fn emitted(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.emission_strength * self.emission.value_at(hit_rec)
+    }
+
+    fn albedo(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.base_color.value_at(hit_rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+    use crate::material::Material;
+    use crate::testing::hit_record;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_metallic_roughness_channels() {
+        let texture = Texture::Image {
+            image: Arc::new(Image::new(1, 1, vec![Vec3::new(0.1, 0.4, 0.8)])),
+            srgb: false,
+        };
+        let principled =
+            Principled::from_metallic_roughness(Texture::solid(1.0), Some(texture), 0.5, 1.0);
+
+        assert!((principled.metallic.scalar(0.5, 0.5) - 0.4).abs() < 1e-6);
+        assert!((principled.roughness.scalar(0.5, 0.5) - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mtl_conversions() {
+        let rough = Principled::from_mtl(&MtlParams::default());
+        assert_eq!(rough.roughness.scalar(0.0, 0.0), 1.0);
+        assert_eq!(rough.transmission.scalar(0.0, 0.0), 0.0);
+
+        let glossy = Principled::from_mtl(&MtlParams {
+            ns: 30.0,
+            d: 0.4,
+            ..MtlParams::default()
+        });
+        assert!((glossy.roughness.scalar(0.0, 0.0) - 0.5).abs() < 1e-6);
+        assert!((glossy.transmission.scalar(0.0, 0.0) - 0.6).abs() < 1e-6);
+
+        let explicit = Principled::from_mtl(&MtlParams {
+            ns: 30.0,
+            pr: Some(0.2),
+            ..MtlParams::default()
+        });
+        assert_eq!(explicit.roughness.scalar(0.0, 0.0), 0.2);
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        let ray = Ray::new(Vec3::new(1.0, 0.0, 1.0), Vec3::new(-1.0, 0.0, -1.0));
+        for (metallic, clearcoat, transmission) in [
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (0.5, 1.0, 0.0),
+            (0.0, 0.0, 1.0),
+        ] {
+            let mut principled = Principled::new(Texture::solid(1.0));
+            principled.metallic = Texture::solid(metallic);
+            principled.clearcoat = Texture::solid(clearcoat);
+            principled.transmission = Texture::solid(transmission);
+            let material = Material::Principled(Box::new(principled.clone()));
+            let hit_rec = hit_record(&material);
+
+            for _ in 0..2000 {
+                if let Some(scatter) = principled.scatter(&ray, &hit_rec) {
+                    let weight = scatter.attenuation;
+                    assert!(weight.x().max(weight.y()).max(weight.z()) <= 1.0 + 1e-5);
+                }
+            }
+        }
+    }
+}
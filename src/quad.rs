@@ -0,0 +1,160 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Parallelogram spanned by the edge vectors `u` and `v` from corner `q`.
+/// Texture coordinates run from 0 to 1 along each edge.
+pub struct Quad {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    normal: Vec3,
+    d: f32,
+    material: Material,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, material: Material) -> Quad {
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+
+        Quad {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let denom = self.normal.dot(ray.direction);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let point = ray.point_at(t);
+        let p = point - self.q;
+        let alpha = self.w.dot(p.cross(self.v));
+        let beta = self.w.dot(self.u.cross(p));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        if !self.material.alpha_test(alpha, beta) {
+            return None;
+        }
+
+        let front_face = denom < 0.0;
+
+        Some(HitRecord {
+            point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            material: &self.material,
+            t,
+            u: alpha,
+            v: beta,
+            tangent: self.u,
+            bitangent: self.v,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::enclosing(
+            Aabb::from_points(self.q, self.q + self.u + self.v),
+            Aabb::from_points(self.q + self.u, self.q + self.v),
+        )
+        .padded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cutout::{AlphaMode, Cutout};
+    use crate::image::Image;
+    use crate::testing::material;
+    use crate::texture::Texture;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_hit() {
+        let quad = Quad::new(
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            material(),
+        );
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = quad.hit(&ray, Interval::new(0.001, f32::INFINITY)).unwrap();
+
+        assert!((hit_rec.t - 2.0).abs() < 1e-5);
+        assert!(hit_rec.front_face);
+        assert!((hit_rec.u - 0.5).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(1.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+
+    #[test]
+    fn test_cutout_skips_transparent_texels() {
+        let alpha = Texture::Image {
+            image: Arc::new(Image::new(
+                2,
+                1,
+                vec![Vec3::new(1.0, 1.0, 1.0), Vec3::zero()],
+            )),
+            srgb: false,
+        };
+        let quad = Quad::new(
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Material::Cutout(Box::new(Cutout {
+                material: material(),
+                alpha,
+                mode: AlphaMode::Threshold(0.5),
+            })),
+        );
+        let behind = Quad::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            material(),
+        );
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(quad), Box::new(behind)];
+
+        let ray = Ray::new(Vec3::new(-0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = world
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 2.0).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = world
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 3.0).abs() < 1e-5);
+    }
+}
@@ -3,11 +3,26 @@ use crate::vec3::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// Wavelength in nanometers once the path has been split by a dispersive
+    /// material, `None` while it still carries all wavelengths.
+    pub wavelength: Option<f32>,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            wavelength: None,
+        }
+    }
+
+    pub fn with_wavelength(origin: Vec3, direction: Vec3, wavelength: Option<f32>) -> Ray {
+        Ray {
+            origin,
+            direction,
+            wavelength,
+        }
     }
 
     pub fn point_at(&self, t: f32) -> Vec3 {
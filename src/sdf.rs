@@ -0,0 +1,280 @@
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// Signed distance function, negative inside the shape.
+///
+/// Primitives are centered at the origin and can be combined into bigger
+/// shapes. `Function` wraps any user-provided distance function.
+#[derive(Clone)]
+pub enum Sdf {
+    Sphere(f32),
+    /// Box with the given half extents.
+    Cuboid(Vec3),
+    /// Torus around the `y` axis with major and minor radius.
+    Torus(f32, f32),
+    Function(Arc<dyn Fn(Vec3) -> f32 + Send + Sync>),
+    Translate(Box<Sdf>, Vec3),
+    Union(Box<Sdf>, Box<Sdf>),
+    /// Union blending the two shapes together over a distance of about `k`.
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f32),
+    /// Infinite repetition of the shape with the given period along each
+    /// axis. Axes with a period of zero are not repeated.
+    Repeat(Box<Sdf>, Vec3),
+    /// Rotation around the `y` axis growing linearly with the height, in
+    /// radians per unit.
+    Twist(Box<Sdf>, f32),
+}
+
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + h * (a - b) - k * h * (1.0 - h)
+}
+
+impl Sdf {
+    pub fn function(f: impl Fn(Vec3) -> f32 + Send + Sync + 'static) -> Sdf {
+        Sdf::Function(Arc::new(f))
+    }
+
+    pub fn translate(self, offset: Vec3) -> Sdf {
+        Sdf::Translate(Box::new(self), offset)
+    }
+
+    pub fn union(self, other: Sdf) -> Sdf {
+        Sdf::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: Sdf, k: f32) -> Sdf {
+        Sdf::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn repeat(self, period: Vec3) -> Sdf {
+        Sdf::Repeat(Box::new(self), period)
+    }
+
+    pub fn twist(self, rate: f32) -> Sdf {
+        Sdf::Twist(Box::new(self), rate)
+    }
+
+    pub fn distance(&self, p: Vec3) -> f32 {
+        match self {
+            Sdf::Sphere(radius) => p.length() - radius,
+            Sdf::Cuboid(half_extents) => {
+                let q = Vec3::new(
+                    p.x().abs() - half_extents.x(),
+                    p.y().abs() - half_extents.y(),
+                    p.z().abs() - half_extents.z(),
+                );
+                let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0));
+                outside.length() + q.x().max(q.y()).max(q.z()).min(0.0)
+            }
+            Sdf::Torus(major_radius, minor_radius) => {
+                let rho = (p.x() * p.x() + p.z() * p.z()).sqrt() - major_radius;
+                (rho * rho + p.y() * p.y()).sqrt() - minor_radius
+            }
+            Sdf::Function(f) => f(p),
+            Sdf::Translate(sdf, offset) => sdf.distance(p - *offset),
+            Sdf::Union(a, b) => a.distance(p).min(b.distance(p)),
+            Sdf::SmoothUnion(a, b, k) => smooth_min(a.distance(p), b.distance(p), *k),
+            Sdf::Repeat(sdf, period) => {
+                let mut q = p;
+                for axis in 0..3 {
+                    if period.e[axis] > 0.0 {
+                        q.e[axis] -= period.e[axis] * (p.e[axis] / period.e[axis]).round();
+                    }
+                }
+                sdf.distance(q)
+            }
+            Sdf::Twist(sdf, rate) => {
+                let (sin, cos) = (-rate * p.y()).sin_cos();
+                let q = Vec3::new(cos * p.x() - sin * p.z(), p.y(), sin * p.x() + cos * p.z());
+                sdf.distance(q)
+            }
+        }
+    }
+
+    /// Gradient of the distance by central differences, which is the outward
+    /// normal on the surface.
+    pub fn gradient(&self, p: Vec3, h: f32) -> Vec3 {
+        let d = |axis: usize| {
+            let mut offset = Vec3::zero();
+            offset.e[axis] = h;
+            self.distance(p + offset) - self.distance(p - offset)
+        };
+        Vec3::new(d(0), d(1), d(2)) / (2.0 * h)
+    }
+}
+
+/// Shape given by a signed distance function, rendered by sphere tracing
+/// inside a bounding box.
+///
+/// Distances of twisted or otherwise deformed shapes overestimate the true
+/// distance; a `step_scale` below 1 keeps the marching from overshooting them.
+pub struct SdfShape {
+    sdf: Sdf,
+    bbox: Aabb,
+    material: Material,
+    pub max_steps: u32,
+    pub epsilon: f32,
+    pub step_scale: f32,
+}
+
+impl SdfShape {
+    pub fn new(sdf: Sdf, bbox: Aabb, material: Material) -> SdfShape {
+        SdfShape {
+            sdf,
+            bbox,
+            material,
+            max_steps: 256,
+            epsilon: 1e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    pub fn max_steps(mut self, max_steps: u32) -> SdfShape {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn epsilon(mut self, epsilon: f32) -> SdfShape {
+        self.epsilon = epsilon;
+        self
+    }
+
+    pub fn step_scale(mut self, step_scale: f32) -> SdfShape {
+        self.step_scale = step_scale;
+        self
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let outward_normal = self.sdf.gradient(point, self.epsilon).unit_vector();
+
+        // Spherical mapping around the center of the bounding box.
+        let p = (point - self.bbox.centroid()).unit_vector();
+        let (u, tangent) = hittable::azimuth(p);
+        let v = (-p.y()).clamp(-1.0, 1.0).acos() / std::f32::consts::PI;
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.material,
+            t,
+            u,
+            v,
+            tangent,
+            bitangent: outward_normal.cross(tangent),
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        }
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let span = self.bbox.clip(ray, ray_t)?;
+        let speed = ray.direction.length();
+
+        let mut t = span.min;
+        for _ in 0..self.max_steps {
+            // Marching on the absolute distance finds the surface from inside
+            // as well as from outside.
+            let distance = self.sdf.distance(ray.point_at(t)).abs();
+            if distance < self.epsilon {
+                if ray_t.surrounds(t) {
+                    let hit_rec = self.hit_record(ray, t);
+                    if self.material.alpha_test(hit_rec.u, hit_rec.v) {
+                        return Some(hit_rec);
+                    }
+                }
+                // Step past a rejected surface and look for the next one.
+                t += self.epsilon / speed;
+            } else {
+                t += self.step_scale * distance / speed;
+            }
+            if t >= span.max {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    fn unit_box() -> Aabb {
+        Aabb::from_points(Vec3::new(-2.0, -2.0, -2.0), Vec3::new(2.0, 2.0, 2.0))
+    }
+
+    #[test]
+    fn test_sphere() {
+        let shape = SdfShape::new(Sdf::Sphere(1.0), unit_box(), material());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+
+        let hit_rec = shape
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 2.0).abs() < 1e-3);
+        assert!((hit_rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-3);
+        assert!(hit_rec.front_face);
+
+        let hit_rec = shape.hit(&ray, Interval::new(2.1, f32::INFINITY)).unwrap();
+        assert!((hit_rec.t - 3.0).abs() < 1e-3);
+        assert!(!hit_rec.front_face);
+
+        // A surface at the start of the interval is skipped, not a miss.
+        let hit_rec = shape.hit(&ray, Interval::new(2.0, f32::INFINITY)).unwrap();
+        assert!((hit_rec.t - 3.0).abs() < 1e-3);
+
+        let ray = Ray::new(Vec3::new(1.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(shape
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+    }
+
+    #[test]
+    fn test_combinators() {
+        let blob = Sdf::Sphere(0.5)
+            .translate(Vec3::new(-0.5, 0.0, 0.0))
+            .smooth_union(Sdf::Sphere(0.5).translate(Vec3::new(0.5, 0.0, 0.0)), 0.2);
+        // The blend fills in the gap where the spheres touch.
+        assert!(blob.distance(Vec3::new(0.0, 0.2, 0.0)) < 0.0);
+        assert!(
+            Sdf::Sphere(0.5)
+                .union(Sdf::Sphere(0.5))
+                .distance(Vec3::zero())
+                < 0.0
+        );
+
+        let repeated = Sdf::Sphere(0.25).repeat(Vec3::new(1.0, 0.0, 0.0));
+        assert!((repeated.distance(Vec3::new(10.0, 0.0, 0.0)) + 0.25).abs() < 1e-4);
+        assert!((repeated.distance(Vec3::new(0.0, 1.0, 0.0)) - 0.75).abs() < 1e-4);
+
+        let twisted = Sdf::Cuboid(Vec3::new(1.0, 2.0, 0.1)).twist(std::f32::consts::FRAC_PI_2);
+        assert!(twisted.distance(Vec3::new(0.0, 1.0, 0.9)) < 0.0);
+        assert!(twisted.distance(Vec3::new(0.9, 1.0, 0.0)) > 0.0);
+
+        let custom = Sdf::function(|p| p.y() + 1.0);
+        assert!((custom.gradient(Vec3::zero(), 1e-3) - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-3);
+    }
+}
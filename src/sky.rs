@@ -0,0 +1,242 @@
+use crate::light::DirectionalLight;
+use crate::spectrum;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+
+/// Angular diameter of the sun seen from the earth, in degrees.
+const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+
+/// Wavelengths in micrometers standing in for the red, green and blue
+/// channels when attenuating sunlight.
+const RGB_WAVELENGTHS: [f32; 3] = [0.68, 0.55, 0.44];
+
+/// Coefficients `A` to `E` of the Perez sky luminance distribution, as linear
+/// functions `a * turbidity + b`.
+type Perez = [(f32, f32); 5];
+
+const PEREZ_Y: Perez = [
+    (0.1787, -1.4630),
+    (-0.3554, 0.4275),
+    (-0.0227, 5.3251),
+    (0.1206, -2.5771),
+    (-0.0670, 0.3703),
+];
+const PEREZ_X: Perez = [
+    (-0.0193, -0.2592),
+    (-0.0665, 0.0008),
+    (-0.0004, 0.2125),
+    (-0.0641, -0.8989),
+    (-0.0033, 0.0452),
+];
+const PEREZ_Y_CHROMA: Perez = [
+    (-0.0167, -0.2608),
+    (-0.0950, 0.0092),
+    (-0.0079, 0.2102),
+    (-0.0441, -1.6537),
+    (-0.0109, 0.0529),
+];
+
+/// Preetham, Shirley and Smits 1999, "A Practical Analytic Model for
+/// Daylight", as the background of the scene.
+///
+/// The sky is lit by a sun in `sun_direction`, with +y up, north along -z and
+/// east along +x. The sun disc itself is left out of the background; add
+/// `sun_light` to the scene's lights to light the scene with it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    /// Unit direction towards the sun.
+    pub sun_direction: Vec3,
+    /// Haziness of the atmosphere, from 2 for a clear sky to about 10 for a
+    /// hazy one.
+    pub turbidity: f32,
+    /// Scale applied to the sky luminance in kcd/m².
+    pub intensity: f32,
+    pub sun_intensity: f32,
+}
+
+/// Direction for an `elevation` above the horizon and an `azimuth` clockwise
+/// from north, both in degrees.
+fn direction(elevation: f32, azimuth: f32) -> Vec3 {
+    let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+    Vec3::new(
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+        -elevation.cos() * azimuth.cos(),
+    )
+}
+
+fn perez(coefficients: &Perez, turbidity: f32, cos_theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = coefficients.map(|(m, q)| m * turbidity + q);
+    (1.0 + a * (b / cos_theta.max(1e-3)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// Zenith chromaticity polynomial in the sun's zenith angle `theta`.
+fn chromaticity(rows: [[f32; 4]; 3], turbidity: f32, theta: f32) -> f32 {
+    let t = [turbidity * turbidity, turbidity, 1.0];
+    let powers = [theta.powi(3), theta.powi(2), theta, 1.0];
+
+    rows.iter()
+        .zip(t)
+        .map(|(row, t)| t * row.iter().zip(powers).map(|(c, p)| c * p).sum::<f32>())
+        .sum()
+}
+
+impl Sky {
+    /// Sky with the sun at `elevation` degrees above the horizon and
+    /// `azimuth` degrees clockwise from north.
+    pub fn new(elevation: f32, azimuth: f32) -> Sky {
+        Sky {
+            sun_direction: direction(elevation, azimuth),
+            turbidity: 3.0,
+            intensity: 0.05,
+            sun_intensity: 5.0,
+        }
+    }
+
+    /// Sky at `latitude` degrees north on `day` of the year (1 to 365) at
+    /// `hour` of local solar time.
+    pub fn from_time(latitude: f32, day: u32, hour: f32) -> Sky {
+        let declination = (23.44f32).to_radians() * (2.0 * PI * (284 + day) as f32 / 365.0).sin();
+        let hour_angle = (15.0 * (hour - 12.0)).to_radians();
+        let latitude = latitude.to_radians();
+
+        let sin_elevation = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+        let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+        let azimuth = hour_angle
+            .sin()
+            .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos())
+            + PI;
+
+        Sky::new(elevation.to_degrees(), azimuth.to_degrees())
+    }
+
+    pub fn turbidity(mut self, turbidity: f32) -> Sky {
+        self.turbidity = turbidity;
+        self
+    }
+
+    pub fn intensity(mut self, intensity: f32, sun_intensity: f32) -> Sky {
+        self.intensity = intensity;
+        self.sun_intensity = sun_intensity;
+        self
+    }
+
+    /// Linear RGB radiance of the sky in `direction`. Directions below the
+    /// horizon see the sky at the horizon.
+    pub fn radiance(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.unit_vector();
+        let sun = self.sun_direction.unit_vector();
+        let t = self.turbidity;
+
+        let theta_s = sun.y().clamp(0.0, 1.0).acos();
+        let cos_theta = direction.y().max(0.0);
+        let gamma = direction.dot(sun).clamp(-1.0, 1.0).acos();
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = chromaticity(
+            [
+                [0.00166, -0.00375, 0.00209, 0.0],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            ],
+            t,
+            theta_s,
+        );
+        let zenith_y_chroma = chromaticity(
+            [
+                [0.00275, -0.00610, 0.00317, 0.0],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            ],
+            t,
+            theta_s,
+        );
+
+        let relative = |coefficients: &Perez| {
+            perez(coefficients, t, cos_theta, gamma) / perez(coefficients, t, 1.0, theta_s)
+        };
+        let luminance = zenith_y.max(0.0) * relative(&PEREZ_Y);
+        let x = zenith_x * relative(&PEREZ_X);
+        let y = zenith_y_chroma * relative(&PEREZ_Y_CHROMA);
+        if y <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let xyz = Vec3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        let rgb = spectrum::xyz_to_linear_srgb(xyz);
+        self.intensity * Vec3::new(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0))
+    }
+
+    /// Color of sunlight after passing through the atmosphere, from Rayleigh
+    /// scattering and aerosols of the sky's turbidity.
+    pub fn sun_color(&self) -> Vec3 {
+        let sun = self.sun_direction.unit_vector();
+        if sun.y() <= 0.0 {
+            return Vec3::zero();
+        }
+
+        // Kasten-Young relative optical mass.
+        let zenith = sun.y().acos().to_degrees();
+        let mass = 1.0 / (sun.y() + 0.50572 * (96.07995 - zenith).powf(-1.6364));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        let transmittance = RGB_WAVELENGTHS.map(|lambda| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-mass * (rayleigh + aerosol)).exp()
+        });
+        Vec3::new(transmittance[0], transmittance[1], transmittance[2])
+    }
+
+    /// Directional light for the sun of this sky.
+    pub fn sun_light(&self) -> DirectionalLight {
+        DirectionalLight::new(-self.sun_direction, self.sun_color(), self.sun_intensity)
+            .angular_diameter(SUN_ANGULAR_DIAMETER)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_position_from_time() {
+        // Noon at the equator on the March equinox puts the sun overhead.
+        let sky = Sky::from_time(0.0, 80, 12.0);
+        assert!(sky.sun_direction.y() > 0.999);
+
+        // Morning sun in the east, afternoon sun in the west.
+        assert!(Sky::from_time(45.0, 172, 9.0).sun_direction.x() > 0.5);
+        assert!(Sky::from_time(45.0, 172, 15.0).sun_direction.x() < -0.5);
+        assert!(Sky::from_time(45.0, 172, 0.0).sun_direction.y() < 0.0);
+
+        let sky = Sky::new(30.0, 180.0);
+        assert!((sky.sun_direction - Vec3::new(0.0, 0.5, 0.75f32.sqrt())).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_sky_radiance() {
+        let sky = Sky::new(30.0, 90.0);
+
+        let zenith = sky.radiance(Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.z() > zenith.x());
+        let near_sun = sky.radiance(direction(35.0, 90.0));
+        let away = sky.radiance(direction(35.0, 270.0));
+        assert!(near_sun.y() > away.y());
+    }
+
+    #[test]
+    fn test_sun_reddens_at_sunset() {
+        let noon = Sky::new(80.0, 180.0).sun_color();
+        let sunset = Sky::new(3.0, 270.0).sun_color();
+        assert!(noon.x() > sunset.x());
+        assert!(sunset.x() / sunset.z() > noon.x() / noon.z());
+
+        let light = Sky::new(45.0, 0.0).sun_light();
+        assert_eq!(light.direction, -Sky::new(45.0, 0.0).sun_direction);
+        assert_eq!(light.angular_diameter, SUN_ANGULAR_DIAMETER);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::rng;
+use crate::vec3::Vec3;
+
+pub const LAMBDA_MIN: f32 = 380.0;
+pub const LAMBDA_MAX: f32 = 780.0;
+
+/// Average of `xyz_to_linear_srgb(cie_xyz(lambda))` over the visible range,
+/// used to make uniformly sampled wavelengths average out to white.
+const RGB_NORMALIZATION: Vec3 = Vec3 {
+    e: [0.320907, 0.253872, 0.242624],
+};
+
+/// Average of `wavelength_to_rgb` before normalization, which clips the
+/// negative lobes of the sRGB color matching functions.
+const WEIGHT_NORMALIZATION: Vec3 = Vec3 {
+    e: [0.440457, 0.288466, 0.273240],
+};
+
+pub fn sample_wavelength() -> f32 {
+    rng::random_in_range(LAMBDA_MIN, LAMBDA_MAX)
+}
+
+fn gaussian(lambda: f32, mu: f32, sigma_low: f32, sigma_high: f32) -> f32 {
+    let sigma = if lambda < mu { sigma_low } else { sigma_high };
+    let t = (lambda - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 color matching functions, using the multi-lobe fit from Wyman,
+/// Sloan and Shirley 2013, "Simple Analytic Approximations to the CIE XYZ
+/// Color Matching Functions".
+pub fn cie_xyz(lambda: f32) -> Vec3 {
+    let x = 1.056 * gaussian(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * gaussian(lambda, 501.1, 20.4, 26.2);
+    let y =
+        0.821 * gaussian(lambda, 568.8, 46.9, 40.5) + 0.286 * gaussian(lambda, 530.9, 16.3, 31.1);
+    let z =
+        1.217 * gaussian(lambda, 437.0, 11.8, 36.0) + 0.681 * gaussian(lambda, 459.0, 26.0, 13.8);
+
+    Vec3::new(x, y, z)
+}
+
+pub fn xyz_to_linear_srgb(xyz: Vec3) -> Vec3 {
+    Vec3::new(
+        3.2406 * xyz.x() - 1.5372 * xyz.y() - 0.4986 * xyz.z(),
+        -0.9689 * xyz.x() + 1.8758 * xyz.y() + 0.0415 * xyz.z(),
+        0.0557 * xyz.x() - 0.2040 * xyz.y() + 1.0570 * xyz.z(),
+    )
+}
+
+/// Converts XYZ accumulated from uniformly sampled wavelengths to linear sRGB,
+/// scaled so that a flat unit spectrum maps to white.
+pub fn xyz_to_rgb(xyz: Vec3) -> Vec3 {
+    let rgb = xyz_to_linear_srgb(xyz);
+
+    Vec3::new(
+        rgb.x() / RGB_NORMALIZATION.x(),
+        rgb.y() / RGB_NORMALIZATION.y(),
+        rgb.z() / RGB_NORMALIZATION.z(),
+    )
+}
+
+/// RGB weight of a path carrying a single wavelength. Averaged over uniformly
+/// sampled wavelengths it equals white, so it can be used as an attenuation.
+///
+/// Spectral colors lie outside the sRGB gamut, so their negative channels are
+/// clipped to keep the weight, and the radiance it scales, non-negative.
+pub fn wavelength_to_rgb(lambda: f32) -> Vec3 {
+    let rgb = xyz_to_linear_srgb(cie_xyz(lambda));
+
+    Vec3::new(
+        rgb.x().max(0.0) / WEIGHT_NORMALIZATION.x(),
+        rgb.y().max(0.0) / WEIGHT_NORMALIZATION.y(),
+        rgb.z().max(0.0) / WEIGHT_NORMALIZATION.z(),
+    )
+}
+
+/// Crossover wavelengths and edge width of the smooth band spectra used for
+/// RGB upsampling, fitted so that the bands round-trip to their primaries.
+const BLUE_GREEN_EDGE: f32 = 490.0;
+const GREEN_RED_EDGE: f32 = 585.0;
+const EDGE_WIDTH: f32 = 10.0;
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Value at `lambda` of a smooth spectrum with the given linear RGB color,
+/// following Smits 1999, "An RGB to Spectrum Conversion for Reflectances".
+/// The color is split into white plus at most one secondary and one primary
+/// band, so reflectances in `[0, 1]` stay in `[0, 1]` and white stays flat.
+pub fn rgb_to_spectrum(rgb: Vec3, lambda: f32) -> f32 {
+    let red = sigmoid((lambda - GREEN_RED_EDGE) / EDGE_WIDTH);
+    let blue = sigmoid((BLUE_GREEN_EDGE - lambda) / EDGE_WIDTH);
+    let green = 1.0 - red - blue;
+    let (cyan, magenta, yellow) = (1.0 - red, 1.0 - green, 1.0 - blue);
+
+    let (r, g, b) = (rgb.x(), rgb.y(), rgb.z());
+    if r <= g && r <= b {
+        if g <= b {
+            r + (g - r) * cyan + (b - g) * blue
+        } else {
+            r + (b - r) * cyan + (g - b) * green
+        }
+    } else if g <= r && g <= b {
+        if r <= b {
+            g + (r - g) * magenta + (b - r) * blue
+        } else {
+            g + (b - g) * magenta + (r - b) * red
+        }
+    } else if r <= g {
+        b + (r - b) * yellow + (g - r) * green
+    } else {
+        b + (g - b) * yellow + (r - g) * red
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wavelength_weights_average_to_white() {
+        let n = 10000;
+        let mut sum = Vec3::zero();
+        for i in 0..n {
+            let lambda = LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * (i as f32 + 0.5) / n as f32;
+            sum = sum + wavelength_to_rgb(lambda);
+        }
+        let mean = sum / n as f32;
+        assert!((mean - Vec3::new(1.0, 1.0, 1.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_wavelength_weights_are_non_negative() {
+        for i in 0..=400 {
+            let weight = wavelength_to_rgb(LAMBDA_MIN + i as f32);
+            assert!(weight.x() >= 0.0 && weight.y() >= 0.0 && weight.z() >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_upsampled_spectra_round_trip() {
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        assert_eq!(rgb_to_spectrum(white, 450.0), 1.0);
+        assert_eq!(rgb_to_spectrum(white, 700.0), 1.0);
+
+        let n = 2000;
+        for rgb in [
+            Vec3::new(0.8, 0.1, 0.1),
+            Vec3::new(0.2, 0.6, 0.3),
+            Vec3::new(0.1, 0.3, 0.9),
+        ] {
+            let mut xyz = Vec3::zero();
+            for i in 0..n {
+                let lambda = LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * (i as f32 + 0.5) / n as f32;
+                let value = rgb_to_spectrum(rgb, lambda);
+                assert!((0.0..=1.0).contains(&value));
+                xyz = xyz + value * cie_xyz(lambda);
+            }
+            assert!((xyz_to_rgb(xyz / n as f32) - rgb).length() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_spectral_colors() {
+        let red = wavelength_to_rgb(650.0);
+        let blue = wavelength_to_rgb(450.0);
+        assert!(red.x() > red.y() && red.x() > red.z());
+        assert!(blue.z() > blue.x() && blue.z() > blue.y());
+    }
+}
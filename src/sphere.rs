@@ -1,8 +1,10 @@
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
+use std::f32::consts::PI;
 
 pub struct Sphere {
     center: Vec3,
@@ -19,10 +21,41 @@ impl Sphere {
         }
     }
 
-    pub fn hit_record(&self, ray: &Ray, t: f32) -> HitRecord {
+    /// Texture coordinates of a point on the unit sphere, with `v` going from
+    /// the bottom pole to the top one.
+    fn uv(p: Vec3) -> (f32, f32) {
+        let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Partial derivatives of the surface position with respect to `u` and `v`
+    /// at point `p` of the unit sphere.
+    fn derivatives(&self, p: Vec3) -> (Vec3, Vec3) {
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(p.z(), 0.0, -p.x());
+
+        let sin_theta = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        let dpdv = if sin_theta > 1e-6 {
+            PI * self.radius
+                * Vec3::new(
+                    -p.x() * p.y() / sin_theta,
+                    sin_theta,
+                    -p.z() * p.y() / sin_theta,
+                )
+        } else {
+            dpdu.cross(p)
+        };
+
+        (dpdu, dpdv)
+    }
+
+    pub fn hit_record(&self, ray: &Ray, t: f32) -> HitRecord<'_> {
         let point = ray.point_at(t);
         let outward_normal = (point - self.center) / self.radius;
         let front_face = ray.direction.dot(outward_normal) < 0.0;
+        let (u, v) = Sphere::uv(outward_normal);
+        let (tangent, bitangent) = self.derivatives(outward_normal);
         let normal = if front_face {
             outward_normal
         } else {
@@ -34,35 +67,43 @@ impl Sphere {
             normal,
             material: &self.material,
             t,
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
             front_face,
         }
     }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let mut result: Option<HitRecord> = None;
-
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center;
         let a = ray.direction.length_squared();
         let half_b = oc.dot(ray.direction);
         let c = oc.dot(oc) - self.radius * self.radius;
         let discriminant = half_b * half_b - a * c;
 
-        if discriminant >= 0.0 {
-            let sqrt_d = discriminant.sqrt();
-            let mut t = (-half_b - sqrt_d) / a;
+        if discriminant < 0.0 {
+            return None;
+        }
 
-            if !ray_t.surrounds(t) {
-                t = (-half_b + sqrt_d) / a;
-                if ray_t.surrounds(t) {
-                    result = Some(self.hit_record(ray, t));
+        let sqrt_d = discriminant.sqrt();
+        for t in [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a] {
+            if ray_t.surrounds(t) {
+                let hit_rec = self.hit_record(ray, t);
+                if self.material.alpha_test(hit_rec.u, hit_rec.v) {
+                    return Some(hit_rec);
                 }
-            } else {
-                result = Some(self.hit_record(ray, t));
             }
         }
 
-        result
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::from_points(self.center - radius, self.center + radius)
     }
 }
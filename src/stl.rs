@@ -0,0 +1,152 @@
+use crate::material::Material;
+use crate::mesh::MeshData;
+use crate::vec3::Vec3;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Size of the header and of a triangle record in binary STL files.
+const HEADER_SIZE: usize = 84;
+const TRIANGLE_SIZE: usize = 50;
+
+/// Loads an STL mesh in ASCII or binary format. STL stores every triangle
+/// separately with a face normal, so the mesh is flat shaded and its vertices
+/// are not shared.
+pub fn load(path: &str, material: Material) -> Result<MeshData> {
+    let bytes = fs::read(path)?;
+    parse(&bytes, material)
+}
+
+pub fn parse(bytes: &[u8], material: Material) -> Result<MeshData> {
+    // Binary files may also start with "solid", so the size is checked first.
+    let positions = if is_binary(bytes) {
+        parse_binary(bytes)
+    } else if bytes.starts_with(b"solid") {
+        parse_ascii(bytes)?
+    } else {
+        return Err(invalid_data("Not an STL file"));
+    };
+
+    if positions.is_empty() {
+        return Err(invalid_data("STL file has no faces"));
+    }
+
+    let indices = (0..positions.len() as u32 / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+
+    Ok(MeshData::new(positions, indices, material))
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < HEADER_SIZE {
+        return false;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    bytes.len() == HEADER_SIZE + count * TRIANGLE_SIZE
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<Vec3> {
+    bytes[HEADER_SIZE..]
+        .chunks_exact(TRIANGLE_SIZE)
+        .flat_map(|triangle| {
+            let f = |o: usize| {
+                f32::from_le_bytes([
+                    triangle[o],
+                    triangle[o + 1],
+                    triangle[o + 2],
+                    triangle[o + 3],
+                ])
+            };
+            let vertex = |o: usize| Vec3::new(f(o), f(o + 4), f(o + 8));
+            // The face normal comes before the three vertices.
+            [vertex(12), vertex(24), vertex(36)]
+        })
+        .collect()
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Vec3>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid_data("Invalid STL file"))?;
+
+    let mut positions = Vec::new();
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token != "vertex" {
+            continue;
+        }
+        let mut coord = || -> Result<f32> {
+            tokens
+                .next()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid_data("Invalid STL vertex"))
+        };
+        positions.push(Vec3::new(coord()?, coord()?, coord()?));
+    }
+
+    if positions.len() % 3 != 0 {
+        return Err(invalid_data("STL facet without three vertices"));
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    #[test]
+    fn test_parse_ascii() {
+        let stl = "solid test\n\
+            facet normal 0 0 1\n outer loop\n\
+            vertex 0 0 0\n vertex 1 0 0\n vertex 0 1 0\n\
+            endloop\nendfacet\nendsolid test\n";
+        let data = parse(stl.as_bytes(), material()).unwrap();
+        assert_eq!(data.positions[1], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(data.indices, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        // The header starts with "solid" like many exporters write it.
+        let mut bytes = b"solid binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        for offset in [0.0f32, 5.0] {
+            for v in [
+                0.0,
+                0.0,
+                1.0,
+                offset,
+                0.0,
+                0.0,
+                offset + 1.0,
+                0.0,
+                0.0,
+                offset,
+                1.0,
+                0.0,
+            ] {
+                bytes.extend_from_slice(&f32::to_le_bytes(v));
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+
+        let data = parse(&bytes, material()).unwrap();
+        assert_eq!(data.positions.len(), 6);
+        assert_eq!(data.positions[4], Vec3::new(6.0, 0.0, 0.0));
+        assert_eq!(data.indices, vec![[0, 1, 2], [3, 4, 5]]);
+    }
+
+    #[test]
+    fn test_empty_file_is_rejected() {
+        let mut bytes = vec![0; 80];
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        for bytes in [bytes, b"solid empty\nendsolid empty\n".to_vec()] {
+            let error = parse(&bytes, material()).err().map(|e| e.kind());
+            assert_eq!(error, Some(ErrorKind::InvalidData));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::hittable::HitRecord;
+use crate::material::{beer_lambert, Dielectric, Scatter, Scatterable};
+use crate::microfacet;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::rng;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+
+/// Translucent material for skin, wax or marble, scattering light inside the
+/// closed object it is applied to by a volumetric random walk.
+///
+/// Light refracts through a smooth dielectric boundary, then travels
+/// exponentially distributed distances with `mean_free_path` per color
+/// channel between scattering events. Each event continues the path from a
+/// point inside the object, so long walks in bright media need a generous
+/// `max_depth` on the camera.
+#[derive(Clone, Copy)]
+pub struct Subsurface {
+    /// Single-scattering albedo per channel.
+    pub color: Vec3,
+    pub mean_free_path: Vec3,
+    pub ior: f32,
+    /// Henyey-Greenstein asymmetry, from backward (-1) to forward (1)
+    /// scattering.
+    pub anisotropy: f32,
+}
+
+fn mean(v: Vec3) -> f32 {
+    (v.x() + v.y() + v.z()) / 3.0
+}
+
+impl Subsurface {
+    pub fn new(color: Vec3, mean_free_path: Vec3) -> Subsurface {
+        Subsurface {
+            color,
+            mean_free_path,
+            ior: 1.4,
+            anisotropy: 0.0,
+        }
+    }
+
+    pub fn ior(mut self, ior: f32) -> Subsurface {
+        self.ior = ior;
+        self
+    }
+
+    pub fn anisotropy(mut self, anisotropy: f32) -> Subsurface {
+        self.anisotropy = anisotropy;
+        self
+    }
+
+    fn extinction(&self) -> Vec3 {
+        let inverse = |mfp: f32| 1.0 / mfp.max(1e-6);
+        Vec3::new(
+            inverse(self.mean_free_path.x()),
+            inverse(self.mean_free_path.y()),
+            inverse(self.mean_free_path.z()),
+        )
+    }
+
+    /// Samples a direction around `forward` from the Henyey-Greenstein phase
+    /// function.
+    fn sample_phase(&self, forward: Vec3) -> Vec3 {
+        let g = self.anisotropy;
+        let xi = rng::random();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng::random();
+
+        Onb::from_w(forward).local(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
+
+impl Scatterable for Subsurface {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord) -> Option<Scatter> {
+        let boundary = Dielectric::new(self.ior);
+        if hit_rec.front_face {
+            return boundary.scatter(ray_in, hit_rec);
+        }
+
+        // Inside the object: the ray either scatters in the medium before
+        // reaching the surface or crosses the boundary. The distance is
+        // sampled for a random channel and weighted by the average pdf over
+        // all channels.
+        let extinction = self.extinction();
+        let channel = ((rng::random() * 3.0) as usize).min(2);
+        let distance = -(1.0 - rng::random()).ln() / extinction.e[channel];
+        let speed = ray_in.direction.length();
+        let to_surface = hit_rec.t * speed;
+
+        if distance < to_surface {
+            let transmittance = beer_lambert(extinction, distance);
+            let pdf = mean(extinction * transmittance);
+            let direction = ray_in.direction / speed;
+
+            return Some(Scatter {
+                attenuation: self.color * extinction * transmittance / pdf,
+                ray: Ray::with_wavelength(
+                    ray_in.origin + distance * direction,
+                    self.sample_phase(direction),
+                    ray_in.wavelength,
+                ),
+            });
+        }
+
+        let transmittance = beer_lambert(extinction, to_surface);
+        let mut scatter = boundary.scatter(ray_in, hit_rec)?;
+        scatter.attenuation = scatter.attenuation * transmittance / mean(transmittance);
+        Some(scatter)
+    }
+
+    /// Approximates light from `direction` that enters the surface, scatters
+    /// inside and leaves again at the entry point as diffuse transmission
+    /// through the boundary both ways.
+    fn eval(&self, ray_in: &Ray, hit_rec: &HitRecord, direction: Vec3) -> Vec3 {
+        let cos_i = hit_rec.normal.dot(direction);
+        let cos_o = -hit_rec.normal.dot(ray_in.direction.unit_vector());
+        if !hit_rec.front_face || cos_i <= 0.0 || cos_o <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let transmission = (1.0 - microfacet::fresnel_dielectric(cos_i, 1.0 / self.ior))
+            * (1.0 - microfacet::fresnel_dielectric(cos_o, 1.0 / self.ior));
+        transmission * cos_i / PI * self.color
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Vec3 {
+        self.color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::testing::hit_record;
+
+    fn inside_hit(material: &Material) -> HitRecord<'_> {
+        HitRecord {
+            point: Vec3::new(0.0, 0.0, 1.0),
+            normal: Vec3::new(0.0, 0.0, -1.0),
+            front_face: false,
+            ..hit_record(material)
+        }
+    }
+
+    #[test]
+    fn test_dense_medium_scatters_inside() {
+        let subsurface = Subsurface::new(Vec3::new(0.9, 0.5, 0.1), Vec3::new(1e-3, 1e-3, 1e-3));
+        let material = Material::Subsurface(subsurface);
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0));
+
+        for _ in 0..100 {
+            let scatter = subsurface.scatter(&ray, &inside_hit(&material)).unwrap();
+            assert!(scatter.ray.origin.z() < 0.1);
+            assert!((scatter.attenuation - subsurface.color).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_chromatic_transmittance() {
+        let subsurface = Subsurface::new(Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 0.5, 0.25));
+        let material = Material::Subsurface(subsurface);
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0));
+
+        // Paths reaching the surface carry the transmittance of each channel
+        // on average, whichever channel sampled the distance.
+        let n = 50000;
+        let mut sum = Vec3::zero();
+        for _ in 0..n {
+            let scatter = subsurface.scatter(&ray, &inside_hit(&material)).unwrap();
+            if scatter.ray.origin == Vec3::new(0.0, 0.0, 1.0) {
+                sum = sum + scatter.attenuation;
+            }
+        }
+        let estimate = sum / n as f32;
+        let expected = Vec3::new((-1.0f32).exp(), (-2.0f32).exp(), (-4.0f32).exp());
+        assert!((estimate - expected).length() < 0.02);
+    }
+
+    #[test]
+    fn test_eval_transmits_at_entry() {
+        let subsurface = Subsurface::new(Vec3::new(0.8, 0.4, 0.2), Vec3::new(1.0, 1.0, 1.0));
+        let material = Material::Subsurface(subsurface);
+        let hit_rec = hit_record(&material);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let r0 = ((1.4f32 - 1.0) / (1.4 + 1.0)).powi(2);
+        let expected = (1.0 - r0).powi(2) / PI * subsurface.color;
+        let bsdf = material.eval(&ray, &hit_rec, Vec3::new(0.0, 0.0, 1.0));
+        assert!((bsdf - expected).length() < 1e-5);
+
+        assert_eq!(
+            material.eval(&ray, &hit_rec, Vec3::new(0.0, 0.0, -1.0)),
+            Vec3::zero()
+        );
+        assert_eq!(
+            material.eval(&ray, &inside_hit(&material), Vec3::new(0.0, 0.0, -1.0)),
+            Vec3::zero()
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Fixtures shared by the unit tests.
+
+use crate::hittable::HitRecord;
+use crate::material::{Lambertian, Material};
+use crate::vec3::Vec3;
+
+/// Mid-grey diffuse material for tests that only care about geometry.
+pub fn material() -> Material {
+    Material::Lambertian(Lambertian {
+        albedo: Vec3::new(0.5, 0.5, 0.5),
+    })
+}
+
+/// Front-facing hit at the origin of a surface facing +z, with its tangent
+/// frame along the x and y axes.
+pub fn hit_record(material: &Material) -> HitRecord<'_> {
+    HitRecord {
+        point: Vec3::zero(),
+        normal: Vec3::new(0.0, 0.0, 1.0),
+        material,
+        t: 1.0,
+        u: 0.5,
+        v: 0.5,
+        tangent: Vec3::new(1.0, 0.0, 0.0),
+        bitangent: Vec3::new(0.0, 1.0, 0.0),
+        vertex_color: Vec3::new(1.0, 1.0, 1.0),
+        front_face: true,
+    }
+}
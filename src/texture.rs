@@ -0,0 +1,96 @@
+use crate::hittable::HitRecord;
+use crate::image::Image;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// Spatially varying material input, looked up by surface coordinates `(u, v)`
+/// with `v` pointing up.
+#[derive(Clone)]
+pub enum Texture {
+    Solid(Vec3),
+    Image {
+        image: Arc<Image>,
+        srgb: bool,
+    },
+    /// Broadcasts one channel of the inner texture to all three, e.g. the
+    /// roughness stored in the green channel of a glTF metallic-roughness map.
+    /// Indices past the last channel read the blue channel.
+    Channel(Box<Texture>, usize),
+    /// Multiplies the inner texture by a constant factor.
+    Scale(Box<Texture>, Vec3),
+    /// Color interpolated from the vertices of a mesh, white elsewhere.
+    VertexColor,
+}
+
+pub(crate) fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl Texture {
+    pub fn solid(value: f32) -> Texture {
+        Texture::Solid(Vec3::new(value, value, value))
+    }
+
+    pub fn value(&self, u: f32, v: f32) -> Vec3 {
+        self.evaluate(u, v, Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    /// Looks the texture up at a surface hit, including its vertex color.
+    pub fn value_at(&self, hit_rec: &HitRecord) -> Vec3 {
+        self.evaluate(hit_rec.u, hit_rec.v, hit_rec.vertex_color)
+    }
+
+    fn evaluate(&self, u: f32, v: f32, vertex_color: Vec3) -> Vec3 {
+        match self {
+            Texture::Solid(color) => *color,
+            Texture::Image { image, srgb } => {
+                let color = image.sample_bilinear(u, 1.0 - v);
+                if *srgb {
+                    Vec3::new(
+                        srgb_to_linear(color.x()),
+                        srgb_to_linear(color.y()),
+                        srgb_to_linear(color.z()),
+                    )
+                } else {
+                    color
+                }
+            }
+            Texture::Channel(texture, channel) => {
+                let x = texture.evaluate(u, v, vertex_color).e[(*channel).min(2)];
+                Vec3::new(x, x, x)
+            }
+            Texture::Scale(texture, factor) => texture.evaluate(u, v, vertex_color) * *factor,
+            Texture::VertexColor => vertex_color,
+        }
+    }
+
+    /// Scalar lookup for single-valued inputs such as roughness.
+    pub fn scalar(&self, u: f32, v: f32) -> f32 {
+        self.value(u, v).x()
+    }
+}
+
+impl From<Vec3> for Texture {
+    fn from(color: Vec3) -> Texture {
+        Texture::Solid(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_is_clamped() {
+        let color = Box::new(Texture::Solid(Vec3::new(0.1, 0.2, 0.3)));
+
+        let green = Texture::Channel(color.clone(), 1);
+        assert_eq!(green.value(0.5, 0.5), Vec3::new(0.2, 0.2, 0.2));
+        let past_blue = Texture::Channel(color, 7);
+        assert_eq!(past_blue.value(0.5, 0.5), Vec3::new(0.3, 0.3, 0.3));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::aabb::Aabb;
+use crate::hittable::{self, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// Torus around the `y` axis, with the center of its tube `major_radius` away
+/// from `center`.
+pub struct Torus {
+    center: Vec3,
+    major_radius: f32,
+    minor_radius: f32,
+    material: Material,
+}
+
+/// Largest real root of `x^3 + a x^2 + b x + c`.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+
+    let z = if discriminant > 0.0 {
+        let sqrt_d = discriminant.sqrt();
+        (-q / 2.0 + sqrt_d).cbrt() + (-q / 2.0 - sqrt_d).cbrt()
+    } else {
+        let r = (-p / 3.0).sqrt();
+        let cos = if r > 0.0 {
+            (-q / (2.0 * r * r * r)).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        2.0 * r * (cos.acos() / 3.0).cos()
+    };
+
+    z - a / 3.0
+}
+
+fn quadratic_roots(b: f64, c: f64, roots: &mut Vec<f64>) {
+    let discriminant = b * b - 4.0 * c;
+    if discriminant >= 0.0 {
+        let sqrt_d = discriminant.sqrt();
+        roots.push((-b - sqrt_d) / 2.0);
+        roots.push((-b + sqrt_d) / 2.0);
+    }
+}
+
+/// Real roots of `c[0] x^4 + c[1] x^3 + c[2] x^2 + c[3] x + c[4]` using
+/// Ferrari's method, polished with a few Newton iterations.
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    let [a, b, cc, d] = [c[1] / c[0], c[2] / c[0], c[3] / c[0], c[4] / c[0]];
+
+    // Depressed quartic y^4 + p y^2 + q y + r with x = y - a / 4.
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = cc - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * cc / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut roots = Vec::with_capacity(4);
+    if q.abs() < 1e-12 {
+        let mut squares = Vec::with_capacity(2);
+        quadratic_roots(p, r, &mut squares);
+        for z in squares.into_iter().filter(|z| *z >= 0.0) {
+            roots.push(z.sqrt());
+            roots.push(-z.sqrt());
+        }
+    } else {
+        // Positive root of the resolvent cubic splits the quartic into two
+        // quadratics.
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        if m <= 0.0 {
+            return roots;
+        }
+        let s = (2.0 * m).sqrt();
+        quadratic_roots(s, p / 2.0 + m - q / (2.0 * s), &mut roots);
+        quadratic_roots(-s, p / 2.0 + m + q / (2.0 * s), &mut roots);
+    }
+
+    for root in roots.iter_mut() {
+        *root -= a / 4.0;
+        for _ in 0..2 {
+            let x = *root;
+            let f = (((c[0] * x + c[1]) * x + c[2]) * x + c[3]) * x + c[4];
+            let df = ((4.0 * c[0] * x + 3.0 * c[1]) * x + 2.0 * c[2]) * x + c[3];
+            if df.abs() > 1e-12 {
+                *root = x - f / df;
+            }
+        }
+    }
+    roots
+}
+
+impl Torus {
+    pub fn new(center: Vec3, major_radius: f32, minor_radius: f32, material: Material) -> Torus {
+        Torus {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32) -> HitRecord<'_> {
+        let point = ray.point_at(t);
+        let p = point - self.center;
+
+        let rho = (p.x() * p.x() + p.z() * p.z()).sqrt().max(1e-6);
+        let radial = Vec3::new(p.x() / rho, 0.0, p.z() / rho);
+        let outward_normal = (p - self.major_radius * radial) / self.minor_radius;
+
+        let (u, dpdu) = hittable::azimuth(p);
+        let theta = p.y().atan2(rho - self.major_radius);
+        let v = (theta as f64 / (2.0 * PI)) as f32 + 0.5;
+        let dpdv = (2.0 * PI) as f32
+            * (-p.y() * radial + (rho - self.major_radius) * Vec3::new(0.0, 1.0, 0.0));
+
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        HitRecord {
+            point,
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            material: &self.material,
+            t,
+            u,
+            v,
+            tangent: dpdu,
+            bitangent: dpdv,
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        // Solve in double precision along a normalized direction starting
+        // from the point of the ray closest to the center, which keeps the
+        // quartic well conditioned.
+        let scale = ray.direction.length() as f64;
+        let d = [0, 1, 2].map(|i| ray.direction.e[i] as f64 / scale);
+        let o = [0, 1, 2].map(|i| (ray.origin.e[i] - self.center.e[i]) as f64);
+        let s0 = -(o[0] * d[0] + o[1] * d[1] + o[2] * d[2]);
+        let q = [0, 1, 2].map(|i| o[i] + s0 * d[i]);
+
+        let big_r2 = (self.major_radius as f64).powi(2);
+        let small_r2 = (self.minor_radius as f64).powi(2);
+        let qd = q[0] * d[0] + q[1] * d[1] + q[2] * d[2];
+        let qq = q[0] * q[0] + q[1] * q[1] + q[2] * q[2];
+        let k = qq + big_r2 - small_r2;
+        let dxz = d[0] * d[0] + d[2] * d[2];
+        let qdxz = q[0] * d[0] + q[2] * d[2];
+        let qxz = q[0] * q[0] + q[2] * q[2];
+
+        let roots = solve_quartic([
+            1.0,
+            4.0 * qd,
+            4.0 * qd * qd + 2.0 * k - 4.0 * big_r2 * dxz,
+            4.0 * qd * k - 8.0 * big_r2 * qdxz,
+            k * k - 4.0 * big_r2 * qxz,
+        ]);
+
+        let mut candidates = [(f32::NAN, ()); 4];
+        for (candidate, s) in candidates.iter_mut().zip(roots) {
+            candidate.0 = ((s + s0) / scale) as f32;
+        }
+
+        hittable::closest_hit(&mut candidates, ray_t, |t, _| self.hit_record(ray, t))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(outer, self.minor_radius, outer);
+        Aabb::from_points(self.center - extent, self.center + extent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::material;
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x - 1)(x - 2)(x + 3)(x - 0.5)
+        let mut roots = solve_quartic([1.0, -0.5, -7.0, 9.5, -3.0]);
+        roots.sort_by(|a, b| a.total_cmp(b));
+        let expected = [-3.0, 0.5, 1.0, 2.0];
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+
+        assert!(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn test_hit() {
+        let torus = Torus::new(Vec3::new(0.0, 0.0, -10.0), 2.0, 0.5, material());
+
+        // Through the hole along the axis.
+        let ray = Ray::new(Vec3::new(0.0, 5.0, -10.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .is_none());
+
+        // Along the x axis through both sides of the tube.
+        let ray = Ray::new(Vec3::new(-10.0, 0.0, -10.0), Vec3::new(2.0, 0.0, 0.0));
+        let hit_rec = torus
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 3.75).abs() < 1e-4);
+        assert!((hit_rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-4);
+
+        let hit_rec = torus.hit(&ray, Interval::new(4.0, f32::INFINITY)).unwrap();
+        assert!((hit_rec.t - 4.25).abs() < 1e-4);
+        assert!(!hit_rec.front_face);
+
+        // Outermost point of the tube on the +z side.
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let hit_rec = torus
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert!((hit_rec.t - 7.5).abs() < 1e-4);
+        assert!((hit_rec.u - 0.25).abs() < 1e-5);
+        assert!((hit_rec.v - 0.5).abs() < 1e-5);
+        let expected = Vec3::new(5.0 * PI as f32, 0.0, 0.0);
+        assert!((hit_rec.tangent - expected).length() < 1e-3);
+    }
+}
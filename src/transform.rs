@@ -0,0 +1,223 @@
+use crate::vec3::Vec3;
+use std::ops;
+
+/// Affine transformation stored as the top three rows of a 4x4 matrix,
+/// together with its inverse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: [[f32; 4]; 3],
+    inv: [[f32; 4]; 3],
+}
+
+const IDENTITY: [[f32; 4]; 3] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+];
+
+fn multiply(a: &[[f32; 4]; 3], b: &[[f32; 4]; 3]) -> [[f32; 4]; 3] {
+    let mut m = [[0.0; 4]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum::<f32>();
+        }
+        row[3] += a[i][3];
+    }
+    m
+}
+
+fn invert(m: &[[f32; 4]; 3]) -> Option<[[f32; 4]; 3]> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+    let det = (0..3).map(|k| m[0][k] * adjugate[k][0]).sum::<f32>();
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let mut inv = [[0.0; 4]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            inv[i][j] = adjugate[i][j] / det;
+        }
+        inv[i][3] = -(0..3).map(|k| inv[i][k] * m[k][3]).sum::<f32>();
+    }
+    Some(inv)
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            m: IDENTITY,
+            inv: IDENTITY,
+        }
+    }
+
+    /// Builds a transform from the top three rows of a 4x4 matrix, or `None`
+    /// if the matrix is singular.
+    pub fn from_rows(m: [[f32; 4]; 3]) -> Option<Transform> {
+        invert(&m).map(|inv| Transform { m, inv })
+    }
+
+    /// Builds a transform from a column-major 4x4 matrix, as used by glTF.
+    pub fn from_columns(columns: [[f32; 4]; 4]) -> Option<Transform> {
+        let row = |i: usize| [columns[0][i], columns[1][i], columns[2][i], columns[3][i]];
+        Transform::from_rows([row(0), row(1), row(2)])
+    }
+
+    pub fn translation(offset: Vec3) -> Transform {
+        let rows = |o: Vec3| {
+            [
+                [1.0, 0.0, 0.0, o.x()],
+                [0.0, 1.0, 0.0, o.y()],
+                [0.0, 0.0, 1.0, o.z()],
+            ]
+        };
+        Transform {
+            m: rows(offset),
+            inv: rows(-offset),
+        }
+    }
+
+    pub fn scaling(scale: Vec3) -> Transform {
+        Transform::from_rows([
+            [scale.x(), 0.0, 0.0, 0.0],
+            [0.0, scale.y(), 0.0, 0.0],
+            [0.0, 0.0, scale.z(), 0.0],
+        ])
+        .expect("Unable to invert zero scale")
+    }
+
+    /// Rotation by `degrees` counterclockwise around `axis`.
+    pub fn rotation(axis: Vec3, degrees: f32) -> Transform {
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        let m = [
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+        ];
+        let mut inv = IDENTITY;
+        for i in 0..3 {
+            for j in 0..3 {
+                inv[i][j] = m[j][i];
+            }
+        }
+        Transform { m, inv }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        let row = |r: &[f32; 4]| r[0] * p.x() + r[1] * p.y() + r[2] * p.z() + r[3];
+        Vec3::new(row(&self.m[0]), row(&self.m[1]), row(&self.m[2]))
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let row = |r: &[f32; 4]| r[0] * v.x() + r[1] * v.y() + r[2] * v.z();
+        Vec3::new(row(&self.m[0]), row(&self.m[1]), row(&self.m[2]))
+    }
+
+    /// Transforms a surface normal with the inverse transpose, which keeps it
+    /// perpendicular to the surface under non-uniform scaling. The result is
+    /// not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let column =
+            |j: usize| self.inv[0][j] * n.x() + self.inv[1][j] * n.y() + self.inv[2][j] * n.z();
+        Vec3::new(column(0), column(1), column(2))
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// `a * b` applies `b` first and then `a`.
+impl ops::Mul<Transform> for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            m: multiply(&self.m, &other.m),
+            inv: multiply(&other.inv, &self.inv),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_compose_and_invert() {
+        let t = Transform::translation(Vec3::new(1.0, 2.0, 3.0))
+            * Transform::rotation(Vec3::new(0.0, 0.0, 1.0), 90.0)
+            * Transform::scaling(Vec3::new(2.0, 2.0, 2.0));
+        let p = Vec3::new(1.0, 0.0, 0.0);
+
+        assert_near(t.point(p), Vec3::new(1.0, 4.0, 3.0));
+        assert_near(t.inverse().point(t.point(p)), p);
+        assert_near(t.vector(p), Vec3::new(0.0, 2.0, 0.0));
+
+        let general = Transform::from_rows(t.m).unwrap();
+        assert_near(general.inverse().point(t.point(p)), p);
+    }
+
+    #[test]
+    fn test_normals_stay_perpendicular() {
+        let t = Transform::scaling(Vec3::new(4.0, 1.0, 1.0));
+        let tangent = Vec3::new(1.0, -1.0, 0.0);
+        let normal = Vec3::new(1.0, 1.0, 0.0);
+        assert!(t.vector(tangent).dot(t.normal(normal)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_singular_matrix_is_rejected() {
+        assert!(Transform::from_rows([[0.0; 4]; 3]).is_none());
+    }
+}
@@ -0,0 +1,389 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{Isotropic, Material};
+use crate::ray::Ray;
+use crate::rng;
+use crate::vec3::Vec3;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Storage type of the voxels in a grid file, always little endian.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VoxelFormat {
+    /// Bytes mapped to densities in `[0, 1]`.
+    U8,
+    F32,
+}
+
+impl VoxelFormat {
+    fn size(self) -> usize {
+        match self {
+            VoxelFormat::U8 => 1,
+            VoxelFormat::F32 => 4,
+        }
+    }
+}
+
+/// Densities sampled at the centers of the cells of a 3D grid, stored with
+/// `x` varying fastest, then `y`, then `z`.
+pub struct DensityGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub values: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, values: Vec<f32>) -> DensityGrid {
+        assert_eq!(values.len(), nx * ny * nz);
+        DensityGrid { nx, ny, nz, values }
+    }
+
+    pub fn load(path: &str) -> Result<DensityGrid> {
+        let bytes = fs::read(path)?;
+        DensityGrid::parse(&bytes)
+    }
+
+    /// Parses a grid with a one-line text header `DENSITY <nx> <ny> <nz>
+    /// <u8|f32>` followed by the raw voxels.
+    pub fn parse(bytes: &[u8]) -> Result<DensityGrid> {
+        let end = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or_else(|| invalid_data("Missing grid header"))?;
+        let header =
+            std::str::from_utf8(&bytes[..end]).map_err(|_| invalid_data("Invalid grid header"))?;
+
+        let tokens: Vec<&str> = header.split_whitespace().collect();
+        if tokens.len() != 5 || tokens[0] != "DENSITY" {
+            return Err(invalid_data("Invalid grid header"));
+        }
+        let size = |token: &str| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid_data("Invalid grid size"))
+        };
+        let format = match tokens[4] {
+            "u8" => VoxelFormat::U8,
+            "f32" => VoxelFormat::F32,
+            _ => return Err(invalid_data("Unsupported voxel format")),
+        };
+
+        DensityGrid::from_raw(
+            &bytes[end + 1..],
+            size(tokens[1])?,
+            size(tokens[2])?,
+            size(tokens[3])?,
+            format,
+        )
+    }
+
+    /// Reads headerless voxel data of the given dimensions.
+    pub fn from_raw(
+        bytes: &[u8],
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        format: VoxelFormat,
+    ) -> Result<DensityGrid> {
+        let count = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .ok_or_else(|| invalid_data("Grid is too large"))?;
+        if count == 0 {
+            return Err(invalid_data("Empty grid"));
+        }
+        let len = count
+            .checked_mul(format.size())
+            .ok_or_else(|| invalid_data("Grid is too large"))?;
+        if bytes.len() < len {
+            return Err(invalid_data("Unexpected end of grid data"));
+        }
+
+        let values: Vec<f32> = bytes[..len]
+            .chunks_exact(format.size())
+            .map(|b| match format {
+                VoxelFormat::U8 => b[0] as f32 / 255.0,
+                VoxelFormat::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            })
+            .collect();
+        if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err(invalid_data("Invalid grid density"));
+        }
+        Ok(DensityGrid::new(nx, ny, nz, values))
+    }
+
+    fn value(&self, i: usize, j: usize, k: usize) -> f32 {
+        self.values[(k * self.ny + j) * self.nx + i]
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().fold(0.0, |max, v| max.max(*v))
+    }
+
+    /// Trilinearly interpolated density at normalized coordinates in
+    /// `[0, 1]^3`, clamped at the borders of the grid.
+    pub fn density(&self, p: Vec3) -> f32 {
+        let coordinate = |x: f32, n: usize| {
+            let x = (x * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f32)
+        };
+        let (i0, i1, fx) = coordinate(p.x(), self.nx);
+        let (j0, j1, fy) = coordinate(p.y(), self.ny);
+        let (k0, k1, fz) = coordinate(p.z(), self.nz);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let plane = |k| {
+            lerp(
+                lerp(self.value(i0, j0, k), self.value(i1, j0, k), fx),
+                lerp(self.value(i0, j1, k), self.value(i1, j1, k), fx),
+                fy,
+            )
+        };
+        lerp(plane(k0), plane(k1), fz)
+    }
+}
+
+/// Heterogeneous participating medium filling a box with a density grid.
+///
+/// Absorption and scattering coefficients are multiplied by the density.
+/// Rays collide with the medium by delta tracking against the largest
+/// extinction in the grid, and each collision is returned as a hit with an
+/// `Isotropic` material weighted by the scattering albedo and carrying the
+/// emission of the absorbed part. Shadow rays use ratio tracking.
+pub struct GridVolume {
+    grid: DensityGrid,
+    bbox: Aabb,
+    absorption: f32,
+    scattering: f32,
+    color: Vec3,
+    emission: Vec3,
+    majorant: f32,
+    material: Material,
+}
+
+impl GridVolume {
+    pub fn new(grid: DensityGrid, corner: Vec3, size: Vec3) -> GridVolume {
+        let mut volume = GridVolume {
+            grid,
+            bbox: Aabb::from_points(corner, corner + size),
+            absorption: 0.0,
+            scattering: 1.0,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            emission: Vec3::zero(),
+            majorant: 0.0,
+            material: Material::Isotropic(Isotropic {
+                albedo: Vec3::zero(),
+                emission: Vec3::zero(),
+            }),
+        };
+        volume.update();
+        volume
+    }
+
+    pub fn absorption(mut self, absorption: f32) -> GridVolume {
+        self.absorption = absorption;
+        self.update();
+        self
+    }
+
+    /// Scattering coefficient, with `color` tinting the scattered light.
+    pub fn scattering(mut self, scattering: f32, color: Vec3) -> GridVolume {
+        self.scattering = scattering;
+        self.color = color;
+        self.update();
+        self
+    }
+
+    /// Radiance emitted by the absorbing part of the medium.
+    pub fn emission(mut self, emission: Vec3) -> GridVolume {
+        self.emission = emission;
+        self.update();
+        self
+    }
+
+    fn update(&mut self) {
+        let extinction = self.absorption + self.scattering;
+        self.majorant = extinction * self.grid.max();
+
+        let (albedo, emission) = if extinction > 0.0 {
+            (
+                self.scattering / extinction * self.color,
+                self.absorption / extinction * self.emission,
+            )
+        } else {
+            (Vec3::zero(), Vec3::zero())
+        };
+        self.material = Material::Isotropic(Isotropic { albedo, emission });
+    }
+
+    fn extinction(&self, p: Vec3) -> f32 {
+        let size = Vec3::new(self.bbox.x.size(), self.bbox.y.size(), self.bbox.z.size());
+        let local = p - Vec3::new(self.bbox.x.min, self.bbox.y.min, self.bbox.z.min);
+        let uvw = Vec3::new(
+            local.x() / size.x(),
+            local.y() / size.y(),
+            local.z() / size.z(),
+        );
+        (self.absorption + self.scattering) * self.grid.density(uvw)
+    }
+
+    /// Steps through the medium by exponential free flights against the
+    /// majorant, calling `collide` with each tentative collision until it
+    /// returns `false`. Returns `t` of the last collision, or `None` once the
+    /// ray leaves `span`.
+    fn track(
+        &self,
+        ray: &Ray,
+        span: Interval,
+        mut collide: impl FnMut(f32) -> bool,
+    ) -> Option<f32> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        let speed = ray.direction.length();
+
+        let mut t = span.min;
+        loop {
+            t -= (1.0 - rng::random()).ln() / (self.majorant * speed);
+            if t >= span.max {
+                return None;
+            }
+            if !collide(t) {
+                return Some(t);
+            }
+        }
+    }
+}
+
+impl Hittable for GridVolume {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let span = self.bbox.clip(ray, ray_t)?;
+
+        // Delta tracking: a tentative collision is real with probability
+        // extinction / majorant.
+        let t = self.track(ray, span, |t| {
+            rng::random() * self.majorant >= self.extinction(ray.point_at(t))
+        })?;
+
+        Some(HitRecord {
+            point: ray.point_at(t),
+            normal: -ray.direction.unit_vector(),
+            material: &self.material,
+            t,
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::zero(),
+            bitangent: Vec3::zero(),
+            vertex_color: Vec3::new(1.0, 1.0, 1.0),
+            front_face: true,
+        })
+    }
+
+    fn transmittance(&self, ray: &Ray, ray_t: Interval) -> f32 {
+        let Some(span) = self.bbox.clip(ray, ray_t) else {
+            return 1.0;
+        };
+
+        // Ratio tracking weights every tentative collision by the chance of
+        // it being a null collision.
+        let mut transmittance = 1.0;
+        self.track(ray, span, |t| {
+            transmittance *= 1.0 - self.extinction(ray.point_at(t)) / self.majorant;
+            true
+        });
+        transmittance
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_interpolate() {
+        let mut bytes = b"DENSITY 2 1 1 f32\n".to_vec();
+        bytes.extend_from_slice(&0.0f32.to_le_bytes());
+        bytes.extend_from_slice(&1.0f32.to_le_bytes());
+        let grid = DensityGrid::parse(&bytes).unwrap();
+
+        assert_eq!(grid.density(Vec3::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.density(Vec3::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.density(Vec3::new(1.0, 0.5, 0.5)), 1.0);
+
+        let grid = DensityGrid::from_raw(&[0, 255, 51], 3, 1, 1, VoxelFormat::U8).unwrap();
+        assert_eq!(grid.values, vec![0.0, 1.0, 0.2]);
+
+        assert!(DensityGrid::parse(b"DENSITY 2 2 2 f32\n\x00\x00").is_err());
+        assert!(DensityGrid::parse(b"VOXELS 1 1 1 u8\n\x00").is_err());
+    }
+
+    #[test]
+    fn test_invalid_grid_is_rejected() {
+        let huge = format!("DENSITY {} 2 2 f32\n", usize::MAX / 2);
+        assert!(DensityGrid::parse(huge.as_bytes()).is_err());
+        let huge = format!("DENSITY {} 1 1 f32\n", usize::MAX / 2);
+        assert!(DensityGrid::parse(huge.as_bytes()).is_err());
+
+        for value in [f32::NAN, f32::INFINITY, -1.0] {
+            let bytes = value.to_le_bytes();
+            assert!(DensityGrid::from_raw(&bytes, 1, 1, 1, VoxelFormat::F32).is_err());
+        }
+    }
+
+    #[test]
+    fn test_transmittance_follows_beer_lambert() {
+        // Density rising linearly along x, so the optical depth through the
+        // unit box is 2 * 0.5 = 1.
+        let values = (0..64).map(|n| (n as f32 + 0.5) / 64.0).collect();
+        let grid = DensityGrid::new(64, 1, 1, values);
+        let volume = GridVolume::new(grid, Vec3::zero(), Vec3::new(1.0, 1.0, 1.0))
+            .absorption(1.0)
+            .scattering(1.0, Vec3::new(1.0, 1.0, 1.0));
+
+        let ray = Ray::new(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(2.0, 0.0, 0.0));
+        let ray_t = Interval::new(0.001, f32::INFINITY);
+        let n = 20000;
+        let ratio = (0..n)
+            .map(|_| volume.transmittance(&ray, ray_t))
+            .sum::<f32>()
+            / n as f32;
+        let delta = (0..n).filter(|_| volume.hit(&ray, ray_t).is_none()).count() as f32 / n as f32;
+
+        let expected = (-1.0f32).exp();
+        assert!((ratio - expected).abs() < 0.01);
+        assert!((delta - expected).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_collision_material() {
+        let grid = DensityGrid::new(1, 1, 1, vec![1.0]);
+        let volume = GridVolume::new(grid, Vec3::zero(), Vec3::new(1.0, 1.0, 1.0))
+            .absorption(3.0)
+            .scattering(1.0, Vec3::new(1.0, 0.5, 0.5))
+            .emission(Vec3::new(4.0, 4.0, 4.0));
+
+        let ray = Ray::new(Vec3::new(0.5, 0.5, -100.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit_rec = (0..100)
+            .find_map(|_| volume.hit(&ray, Interval::new(0.001, f32::INFINITY)))
+            .unwrap();
+        assert!(hit_rec.t > 100.0 && hit_rec.t < 101.0);
+        match hit_rec.material {
+            Material::Isotropic(isotropic) => {
+                assert_eq!(isotropic.albedo, Vec3::new(0.25, 0.125, 0.125));
+                assert_eq!(isotropic.emission, Vec3::new(3.0, 3.0, 3.0));
+            }
+            _ => panic!("Expected an isotropic material"),
+        }
+    }
+}